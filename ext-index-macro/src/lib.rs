@@ -2,17 +2,20 @@ use proc_macro::TokenStream;
 use quote::{quote, ToTokens};
 use syn::{parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Fields, FieldsNamed, LitStr, Token};
 
-const TANTIVY_EXT_TYPES: [&str; 10] = [
+const TANTIVY_EXT_TYPES: [&str; 13] = [
     "Tokenized",
     "Str",
     "FastStr",
     "U64",
     "FastU64",
+    "I64",
+    "FastI64",
     "F64",
     "FastF64",
     "U64",
     "Date",
     "Score",
+    "SortValues",
 ];
 
 #[proc_macro_derive(TantivySearchIndex, attributes(tantivy_ext))]
@@ -20,6 +23,7 @@ pub fn derive_index(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let struct_name = &input.ident;
+    let struct_attrs = input.attrs.clone();
     let struct_fields = match input.data {
         Data::Struct(data_struct) => match data_struct.fields {
             Fields::Named(fields_named) => fields_named,
@@ -28,6 +32,7 @@ pub fn derive_index(input: TokenStream) -> TokenStream {
         _ => panic!("Index macro only supports structs"),
     };
     let fields: Vec<syn::Ident> = extract_struct_fields_that_arent_score(&struct_fields);
+    let index_sort = index_sort_from_attrs(&struct_attrs, &struct_fields);
 
     let ext_types = get_tantivy_ext_types(&struct_fields);
     let ext_types_tokens = ident_vec_comma_separated(ext_types);
@@ -38,6 +43,7 @@ pub fn derive_index(input: TokenStream) -> TokenStream {
     let mut from_doc_lines = Vec::new();
 
     let mut primary_key_line = None;
+    let mut sort_values_fn = None;
 
     for field in &struct_fields.named {
         let field_name = field.ident.as_ref().unwrap();
@@ -68,6 +74,14 @@ pub fn derive_index(input: TokenStream) -> TokenStream {
             });
         }
         from_doc_lines.push(field_from_doc(field_type, &field_name));
+
+        if is_sort_values(field_type) {
+            sort_values_fn = Some(quote! {
+                fn set_sort_values(&mut self, sort_values: ::tantivy_ext::SortValues) {
+                    self.#field_name = sort_values;
+                }
+            });
+        }
     }
     let model_fields_struct = create_model_fields_struct(struct_name, &fields);
     let model_fields_fn = create_model_fields_fn(struct_name, &fields, &schema_lines);
@@ -111,11 +125,14 @@ pub fn derive_index(input: TokenStream) -> TokenStream {
                 }
             }
 
+            #sort_values_fn
+
             fn index_builder(path: std::path::PathBuf) -> ::tantivy_ext::index::index_builder::SearchIndexBuilder<Self>
             where
                 Self: std::marker::Sized,
             {
                 ::tantivy_ext::index::index_builder::SearchIndexBuilder::new(path)
+                    #index_sort
             }
         }
         impl #struct_name{
@@ -128,6 +145,77 @@ pub fn derive_index(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Whether the field is the `SortValues` field that `QueryBuilder::order_by` fills in
+fn is_sort_values(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "SortValues"),
+        _ => false,
+    }
+}
+
+/// Reads the struct level `#[tantivy_ext(sort_by = "popularity", desc)]` attribute
+/// and creates the builder call that sets up the index sort:
+/// ```rust
+/// .with_index_sort(Self::popularity_field(), ::tantivy::Order::Desc)
+/// ```
+fn index_sort_from_attrs(
+    attrs: &[syn::Attribute],
+    struct_fields: &FieldsNamed,
+) -> Option<proc_macro2::TokenStream> {
+    let attr = attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("tantivy_ext"))?;
+
+    let mut sort_by = None;
+    let mut desc = false;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("sort_by") {
+            sort_by = Some(meta.value()?.parse::<LitStr>()?.value());
+        } else if meta.path.is_ident("desc") {
+            desc = true;
+        } else if meta.path.is_ident("asc") {
+            desc = false;
+        } else {
+            return Err(meta.error("expected `sort_by`, `asc` or `desc`"));
+        }
+        Ok(())
+    })
+    .unwrap_or_else(|err| panic!("Invalid tantivy_ext attribute: {}", err));
+
+    let sort_by = sort_by.unwrap_or_else(|| {
+        panic!("Missing the field to sort by. Consider `#[tantivy_ext(sort_by = \"field\")]`")
+    });
+    let field = struct_fields
+        .named
+        .iter()
+        .find(|field| *field.ident.as_ref().unwrap() == sort_by)
+        .unwrap_or_else(|| panic!("Cannot sort by `{}`: there is no such field", sort_by));
+    if let syn::Type::Path(type_path) = &field.ty {
+        let type_str = type_path.path.segments.last().unwrap().ident.to_string();
+        match type_str.as_str() {
+            "U64" | "FastU64" | "I64" | "FastI64" | "F64" | "FastF64" => {}
+            _ => panic!("Cannot sort by `{}`: index sorting requires a `U64`, `FastU64`, `I64`, `FastI64`, `F64` or `FastF64` field, not `{}`", sort_by, type_str),
+        }
+    }
+
+    let field_fn_name = proc_macro2::Ident::new(
+        &format!("{}_field", sort_by),
+        proc_macro2::Span::call_site(),
+    );
+    let order = if desc {
+        quote! {::tantivy::Order::Desc}
+    } else {
+        quote! {::tantivy::Order::Asc}
+    };
+    Some(quote! {
+        .with_index_sort(Self::#field_fn_name(), #order)
+    })
+}
+
 /// Creates this:
 /// ```rust
 /// struct MyModelFields {
@@ -263,6 +351,11 @@ fn field_from_doc(ty: &syn::Type, field_name: &syn::Ident) -> proc_macro2::Token
                         let #field_name = ::tantivy_ext::field_extractor::field_as_u64(schema, &doc,stringify!(#field_name)).unwrap();
                     }
                 }
+                "FastI64" | "I64" => {
+                    quote! {
+                        let #field_name = ::tantivy_ext::field_extractor::field_as_i64(schema, &doc,stringify!(#field_name)).unwrap();
+                    }
+                }
                 "FastF64" | "F64" => {
                     quote! {
                         let #field_name = ::tantivy_ext::field_extractor::field_as_f64(schema, &doc,stringify!(#field_name)).unwrap();
@@ -278,6 +371,11 @@ fn field_from_doc(ty: &syn::Type, field_name: &syn::Ident) -> proc_macro2::Token
                         let #field_name = score;
                     }
                 }
+                "SortValues" => {
+                    quote! {
+                        let #field_name = ::tantivy_ext::SortValues::default();
+                    }
+                }
                 _ => panic!("Unsupported field type: {}", type_str),
             }
         } else {
@@ -310,11 +408,14 @@ fn schema_field_from_type(ty: &syn::Type, field_name: &str) -> Option<proc_macro
                 "U64" | "FastU64" => Some(
                     quote! { schema_builder.add_u64_field(#field_name, tantivy::schema::FAST | tantivy::schema::STORED); },
                 ),
+                "I64" | "FastI64" => Some(
+                    quote! { schema_builder.add_i64_field(#field_name, tantivy::schema::FAST | tantivy::schema::STORED); },
+                ),
                 "F64" | "FastF64" => Some(
                     quote! { schema_builder.add_f64_field(#field_name, tantivy::schema::FAST | tantivy::schema::STORED); },
                 ),
                 "Date" => Some(
-                    quote! { schema_builder.add_date_field(#field_name, tantivy::schema::INDEXED | tantivy::schema::STORED); },
+                    quote! { schema_builder.add_date_field(#field_name, tantivy::schema::INDEXED | tantivy::schema::STORED | tantivy::schema::FAST); },
                 ),
                 _ => None, // Unknown field. Don't include it in the schema
             }
@@ -339,8 +440,11 @@ fn syn_type_to_ext_type_fallible(ty: &syn::Type) -> Result<proc_macro2::TokenStr
             // Get the last path segment. Example: `fields::FastU64` -> `FastU64`
             let type_str = &segment.ident.to_string();
             return match type_str.as_str() {
-                "FastStr" | "Str" | "Tokenized" => Ok(quote! {::tantivy_ext::ext_type::ExtText}),
+                "Str" => Ok(quote! {::tantivy_ext::ext_type::ExtText}),
+                "FastStr" => Ok(quote! {::tantivy_ext::ext_type::ExtFastText}),
+                "Tokenized" => Ok(quote! {::tantivy_ext::ext_type::ExtTokenized}),
                 "U64" | "FastU64" => Ok(quote! {::tantivy_ext::ext_type::ExtU64}),
+                "I64" | "FastI64" => Ok(quote! {::tantivy_ext::ext_type::ExtI64}),
                 "F64" | "FastF64" => Ok(quote! {::tantivy_ext::ext_type::ExtF64}),
                 "Date" => Ok(quote! {::tantivy_ext::ext_type::ExtDate}),
                 _ => Err(format!("Unknown EXT field: {}", type_str)),
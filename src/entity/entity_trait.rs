@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use tantivy::{schema::Schema, TantivyDocument};
 
-use crate::index::index_builder::SearchIndexBuilder;
+use crate::{entity::field::SortValues, index::index_builder::SearchIndexBuilder};
 
 pub trait Index {
     fn schema() -> &'static Schema;
@@ -13,6 +13,9 @@ pub trait Index {
 
     fn from_document(doc: TantivyDocument, score: f32)->Self;
 
+    /// Hands the model the values it was sorted by. Models without a `SortValues` field ignore them
+    fn set_sort_values(&mut self, _sort_values: SortValues) {}
+
     fn index_builder(path: PathBuf) -> SearchIndexBuilder<Self>
     where
         Self: std::marker::Sized;
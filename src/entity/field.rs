@@ -1,6 +1,8 @@
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
+use crate::index::query::sort::SortValue;
+
 use super::util::date_converter;
 
 pub trait Field {
@@ -9,7 +11,7 @@ pub trait Field {
 }
 
 /// Represents a tokenized String:
-/// ```rust
+/// ```text
 /// TEXT | STORED
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,7 +34,7 @@ impl From<String> for Tokenized {
 }
 
 /// Represents an untokenized String:
-/// ```rust
+/// ```text
 /// STRING | STORED
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,7 +57,7 @@ impl From<String> for Str {
 }
 
 /// Represents an untokenized String stored for quick access:
-/// ```rust
+/// ```text
 /// STRING | FAST | STORED
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,7 +81,7 @@ impl From<String> for FastStr {
 }
 
 /// Represents a u64:
-/// ```rust
+/// ```text
 /// STORED
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,7 +100,7 @@ impl From<u64> for U64 {
 }
 
 /// Represents a u64 that is stored for quick access:
-/// ```rust
+/// ```text
 /// FAST | STORED
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,8 +118,46 @@ impl From<u64> for FastU64 {
     }
 }
 
+/// Represents an i64:
+/// ```text
+/// FAST | STORED
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct I64(i64);
+impl Field for I64 {
+    type Target = i64;
+    fn tantivy_val(&self) -> Self::Target {
+        self.0
+    }
+}
+
+impl From<i64> for I64 {
+    fn from(val: i64) -> Self {
+        I64(val)
+    }
+}
+
+/// Represents an i64 that is stored for quick access:
+/// ```text
+/// FAST | STORED
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FastI64(i64);
+impl Field for FastI64 {
+    type Target = i64;
+    fn tantivy_val(&self) -> Self::Target {
+        self.0
+    }
+}
+
+impl From<i64> for FastI64 {
+    fn from(val: i64) -> Self {
+        FastI64(val)
+    }
+}
+
 /// Represents a f64:
-/// ```rust
+/// ```text
 /// STORED
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -136,7 +176,7 @@ impl From<f64> for F64 {
 }
 
 /// Represents a f64 that is stored for quick access:
-/// ```rust
+/// ```text
 /// FAST | STORED
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -171,9 +211,32 @@ impl From<f32> for Score {
     }
 }
 
+/// The values a model was sorted by when it came out of `QueryBuilder::order_by`,
+/// one for every field it was sorted by, in the same order.
+///
+/// Like `Score`, this field is not actually stored in the search index.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SortValues(Vec<SortValue>);
+impl Field for SortValues {
+    type Target = Vec<SortValue>;
+    fn tantivy_val(&self) -> Self::Target {
+        self.0.clone()
+    }
+}
+impl SortValues {
+    pub fn values(&self) -> &[SortValue] {
+        &self.0
+    }
+}
+impl From<Vec<SortValue>> for SortValues {
+    fn from(val: Vec<SortValue>) -> Self {
+        SortValues(val)
+    }
+}
+
 /// Represents a date:
-/// ```rust
-/// INDEXED | STORED
+/// ```text
+/// INDEXED | STORED | FAST
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Date(tantivy::DateTime);
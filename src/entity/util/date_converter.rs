@@ -1,4 +1,4 @@
-use chrono::{NaiveDateTime, Utc};
+use chrono::Utc;
 use tantivy::time::UtcOffset;
 
 pub fn chrono_time_to_tantivy_datetime(chrono_dt: chrono::DateTime<chrono::Utc>) -> tantivy::DateTime {
@@ -11,9 +11,6 @@ pub fn tantivy_time_to_chrono_datetime(tantivy_datetime: tantivy::DateTime) -> c
     let unix_timestamp = offset.unix_timestamp(); // Get seconds since UNIX epoch
     let nanos = offset.nanosecond(); // Get nanoseconds past the second
 
-    // Create a NaiveDateTime from the timestamp
-    let naive_datetime = NaiveDateTime::from_timestamp(unix_timestamp, nanos);
-
-    // Convert NaiveDateTime to DateTime<Utc>
-    chrono::DateTime::<Utc>::from_utc(naive_datetime, Utc)
+    chrono::DateTime::<Utc>::from_timestamp(unix_timestamp, nanos)
+        .expect("tantivy datetime is out of range for chrono")
 }
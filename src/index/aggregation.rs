@@ -0,0 +1,223 @@
+use std::{marker::PhantomData, time::Duration};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+use tantivy::{
+    aggregation::{agg_req::Aggregations, AggregationCollector, AggregationLimits},
+    query::Query,
+    DateTime, Searcher, TantivyError,
+};
+
+use super::ext::{
+    ext_field::ExtField,
+    ext_type::{ExtDate, ExtFastText},
+    ext_type_trait::{ExtNumericType, ExtType},
+};
+
+/// The aggregations to run alongside a query with `SearchIndex::aggregate`.
+///
+/// Every aggregation that gets added hands back a handle, which is used to read its typed result afterwards.
+///
+/// Example:
+/// ```ignore
+/// let mut request = AggregationRequest::new();
+/// let per_name = request.terms(MyModel::name_field(), 10);
+/// let per_day = request.date_histogram(MyModel::date_field(), Duration::from_secs(24 * 60 * 60));
+/// let popularity = request.stats(MyModel::popularity_field());
+///
+/// let response = index.aggregate(&AllQuery, &request)?;
+/// for bucket in response.get(&per_name)?.buckets {
+///     println!("{}: {}", bucket.key, bucket.doc_count);
+/// }
+/// println!("average popularity: {:?}", response.get(&popularity)?.avg);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AggregationRequest {
+    aggregations: Map<String, Value>,
+}
+
+/// Refers to one aggregation of an `AggregationRequest`, and to its result in the `AggregationResponse`
+#[derive(Clone, Debug)]
+pub struct AggregationHandle<T> {
+    name: String,
+    phantom: PhantomData<T>,
+}
+
+impl AggregationRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the models for each of the `size` most common values of a `FastStr` field
+    pub fn terms(
+        &mut self,
+        field: ExtField<ExtFastText>,
+        size: u32,
+    ) -> AggregationHandle<TermsResult> {
+        self.add(
+            "terms",
+            &field,
+            json!({ "terms": { "field": field.name(), "size": size } }),
+        )
+    }
+
+    /// Count the models that fall within each `interval`, from the earliest date to the latest
+    pub fn date_histogram(
+        &mut self,
+        field: ExtField<ExtDate>,
+        interval: Duration,
+    ) -> AggregationHandle<DateHistogramResult> {
+        // tantivy takes fixed intervals as a number with a unit, and milliseconds are the finest unit it knows
+        let interval = format!("{}ms", interval.as_millis().max(1));
+        self.add(
+            "date_histogram",
+            &field,
+            json!({ "date_histogram": { "field": field.name(), "fixed_interval": interval } }),
+        )
+    }
+
+    /// The count, sum, min, max and average of a numeric fast field
+    pub fn stats<T>(&mut self, field: ExtField<T>) -> AggregationHandle<StatsResult>
+    where
+        T: ExtNumericType,
+    {
+        self.add(
+            "stats",
+            &field,
+            json!({ "stats": { "field": field.name() } }),
+        )
+    }
+
+    fn add<T, R>(
+        &mut self,
+        kind: &str,
+        field: &ExtField<T>,
+        aggregation: Value,
+    ) -> AggregationHandle<R>
+    where
+        T: ExtType,
+    {
+        // The position keeps the names unique, even when the same field gets aggregated twice
+        let name = format!("{}_{}_{}", self.aggregations.len(), kind, field.name());
+        self.aggregations.insert(name.clone(), aggregation);
+        AggregationHandle {
+            name,
+            phantom: PhantomData,
+        }
+    }
+
+    fn to_tantivy(&self) -> tantivy::Result<Aggregations> {
+        serde_json::from_value(Value::Object(self.aggregations.clone())).map_err(|err| {
+            TantivyError::InvalidArgument(format!("Invalid aggregation request: {}", err))
+        })
+    }
+}
+
+/// The results of every aggregation of an `AggregationRequest`
+#[derive(Clone, Debug)]
+pub struct AggregationResponse {
+    results: Map<String, Value>,
+}
+
+impl AggregationResponse {
+    /// The result of the aggregation the handle refers to
+    pub fn get<T>(&self, handle: &AggregationHandle<T>) -> tantivy::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let result = self.results.get(&handle.name).ok_or_else(|| {
+            TantivyError::InvalidArgument(format!(
+                "The aggregation `{}` is not part of this response",
+                handle.name
+            ))
+        })?;
+        serde_json::from_value(result.clone()).map_err(|err| {
+            TantivyError::InternalError(format!(
+                "Could not read the result of the aggregation `{}`: {}",
+                handle.name, err
+            ))
+        })
+    }
+
+    /// The raw results, as tantivy serializes them
+    pub fn raw(&self) -> &Map<String, Value> {
+        &self.results
+    }
+}
+
+/// How many models have each of the most common values of a field
+#[derive(Clone, Debug, Deserialize)]
+pub struct TermsResult {
+    /// Most common value first
+    pub buckets: Vec<TermsBucket>,
+    /// How many models have a value that didn't make it into the buckets
+    pub sum_other_doc_count: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TermsBucket {
+    pub key: String,
+    pub doc_count: u64,
+}
+
+/// How many models fall within each interval of a date field
+#[derive(Clone, Debug, Deserialize)]
+pub struct DateHistogramResult {
+    /// Earliest interval first. Intervals without any models in between the first and the last one are included
+    pub buckets: Vec<DateHistogramBucket>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "RawDateHistogramBucket")]
+pub struct DateHistogramBucket {
+    /// The start of the interval
+    pub start: DateTime,
+    pub doc_count: u64,
+}
+
+/// tantivy keys date histogram buckets by their start in milliseconds
+#[derive(Deserialize)]
+struct RawDateHistogramBucket {
+    key: f64,
+    doc_count: u64,
+}
+
+impl From<RawDateHistogramBucket> for DateHistogramBucket {
+    fn from(raw: RawDateHistogramBucket) -> Self {
+        Self {
+            start: DateTime::from_timestamp_millis(raw.key as i64),
+            doc_count: raw.doc_count,
+        }
+    }
+}
+
+/// Statistics over the values of a numeric field. Everything but `count` and `sum` is `None` when no models matched
+#[derive(Clone, Debug, Deserialize)]
+pub struct StatsResult {
+    pub count: u64,
+    pub sum: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+}
+
+/// Runs the aggregations over the models that match the query
+pub(crate) fn aggregate(
+    searcher: &Searcher,
+    query: &dyn Query,
+    request: &AggregationRequest,
+) -> tantivy::Result<AggregationResponse> {
+    let collector =
+        AggregationCollector::from_aggs(request.to_tantivy()?, AggregationLimits::default());
+    let results = searcher.search(query, &collector)?;
+    match serde_json::to_value(results) {
+        Ok(Value::Object(results)) => Ok(AggregationResponse { results }),
+        Ok(_) => Ok(AggregationResponse {
+            results: Map::new(),
+        }),
+        Err(err) => Err(TantivyError::InternalError(format!(
+            "Could not serialize the aggregation results: {}",
+            err
+        ))),
+    }
+}
@@ -1,11 +1,11 @@
 use tantivy::{schema::Schema, IndexReader};
 
-use super::writer_recycler::IndexWriterRecycler;
+use super::writer_actor::WriterHandle;
 
 #[derive(Clone)]
 pub struct TantivyBackend<'a>{
     pub reader: &'a IndexReader,
-    pub writer: &'a IndexWriterRecycler,
+    pub writer: &'a WriterHandle,
     pub index: &'a tantivy::Index,
     pub schema: &'static Schema
 }
\ No newline at end of file
@@ -0,0 +1,15 @@
+/// Runs a blocking tantivy operation, such as a search, on tokio's blocking thread pool
+/// so that it doesn't stall the async worker threads.
+///
+/// Panics in the operation are propagated to the caller.
+pub(crate) async fn run<T, F>(operation: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(operation).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => panic!("blocking tantivy operation was cancelled: {}", err),
+    }
+}
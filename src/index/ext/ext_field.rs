@@ -24,6 +24,14 @@ where
     pub fn term(&self, input: T::Target) -> tantivy::Term {
         self.ext_type.term(input)
     }
+    /// The real field as it is represented in the schema
+    pub fn field(&self) -> Field {
+        *self.ext_type.field()
+    }
+    /// The name of this field as it is represented in the schema
+    pub fn name(&self) -> String {
+        self.ext_type.name()
+    }
 }
 
 impl<T> From<ExtField<T>> for Field
@@ -0,0 +1,335 @@
+use std::ops::{BitAnd, BitOr, Not, RangeBounds};
+
+use tantivy::{
+    query::{
+        AllQuery, BooleanQuery, BoostQuery, EmptyQuery, EnableScoring, Explanation, FuzzyTermQuery,
+        Occur, PhraseQuery, Query, RangeQuery, RegexQuery, TermQuery, TermSetQuery, Weight,
+    },
+    schema::IndexRecordOption,
+    tokenizer::TokenizerManager,
+    DateTime, DocAddress, Searcher, Term,
+};
+
+use super::{
+    ext_field::ExtField,
+    ext_type::{ExtDate, ExtF64, ExtFastText, ExtI64, ExtText, ExtTokenized, ExtU64},
+};
+
+/// A query built from `ExtField` handles.
+///
+/// It can be passed to `SearchIndex::query` as is, combined with `&`, `|` and `!`,
+/// or turned into a `Box<dyn Query>` for anything that takes one of those.
+///
+/// Example:
+/// ```ignore
+/// let query = MyModel::name_field().one_of(["Joe", "Ann"])
+///     & MyModel::popularity_field().range(10.0..)
+///     & !MyModel::path_field().phrase("old reports");
+/// let results = index.query(&query, 10).execute()?;
+/// ```
+#[derive(Debug)]
+pub struct ExtQuery(Box<dyn Query>);
+
+impl ExtQuery {
+    pub fn new<Q>(query: Q) -> Self
+    where
+        Q: Query,
+    {
+        Self(Box::new(query))
+    }
+
+    /// Multiplies the score of every match by `boost`
+    pub fn boost(self, boost: f32) -> Self {
+        Self::new(BoostQuery::new(self.0, boost))
+    }
+
+    pub fn boxed(self) -> Box<dyn Query> {
+        self.0
+    }
+}
+
+impl Clone for ExtQuery {
+    fn clone(&self) -> Self {
+        Self(self.0.box_clone())
+    }
+}
+
+impl Query for ExtQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        self.0.weight(enable_scoring)
+    }
+
+    fn explain(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
+    ) -> tantivy::Result<Explanation> {
+        self.0.explain(searcher, doc_address)
+    }
+
+    fn count(&self, searcher: &Searcher) -> tantivy::Result<usize> {
+        self.0.count(searcher)
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.0.query_terms(visitor)
+    }
+}
+
+impl From<Box<dyn Query>> for ExtQuery {
+    fn from(query: Box<dyn Query>) -> Self {
+        Self(query)
+    }
+}
+
+impl From<ExtQuery> for Box<dyn Query> {
+    fn from(query: ExtQuery) -> Self {
+        query.0
+    }
+}
+
+/// Both sides have to match
+impl<Q> BitAnd<Q> for ExtQuery
+where
+    Q: Query,
+{
+    type Output = ExtQuery;
+
+    fn bitand(self, rhs: Q) -> ExtQuery {
+        ExtQuery::new(BooleanQuery::intersection(vec![self.0, Box::new(rhs)]))
+    }
+}
+
+/// Either side has to match
+impl<Q> BitOr<Q> for ExtQuery
+where
+    Q: Query,
+{
+    type Output = ExtQuery;
+
+    fn bitor(self, rhs: Q) -> ExtQuery {
+        ExtQuery::new(BooleanQuery::union(vec![self.0, Box::new(rhs)]))
+    }
+}
+
+/// Matches every model that the query doesn't match
+impl Not for ExtQuery {
+    type Output = ExtQuery;
+
+    fn not(self) -> ExtQuery {
+        // A boolean query with nothing but exclusions matches nothing, so everything has to be included first
+        ExtQuery::new(BooleanQuery::new(vec![
+            (Occur::Must, Box::new(AllQuery)),
+            (Occur::MustNot, self.0),
+        ]))
+    }
+}
+
+/// Typed queries for `Str` and `FastStr` fields, which are indexed as a single untokenized term
+macro_rules! text_queries {
+    ($ext_type:ty) => {
+        impl ExtField<$ext_type> {
+            /// Matches models where the field is exactly `value`
+            pub fn eq(&self, value: impl Into<String>) -> ExtQuery {
+                ExtQuery::new(TermQuery::new(
+                    self.term(value.into()),
+                    IndexRecordOption::Basic,
+                ))
+            }
+
+            /// Matches models where the field is exactly one of the provided values
+            pub fn one_of<V>(&self, values: impl IntoIterator<Item = V>) -> ExtQuery
+            where
+                V: Into<String>,
+            {
+                ExtQuery::new(TermSetQuery::new(
+                    values.into_iter().map(|value| self.term(value.into())),
+                ))
+            }
+
+            /// Matches models where the field starts with `prefix`
+            pub fn prefix(&self, prefix: impl Into<String>) -> ExtQuery {
+                ExtQuery::new(FuzzyTermQuery::new_prefix(
+                    self.term(prefix.into()),
+                    0,
+                    false,
+                ))
+            }
+
+            /// Matches models where the field is within `distance` edits of `value`. tantivy supports distances of up to 2
+            pub fn fuzzy(&self, value: impl Into<String>, distance: u8) -> ExtQuery {
+                ExtQuery::new(FuzzyTermQuery::new(self.term(value.into()), distance, true))
+            }
+
+            /// Matches models where the whole field matches the regular expression
+            pub fn regex(&self, pattern: &str) -> tantivy::Result<ExtQuery> {
+                Ok(ExtQuery::new(RegexQuery::from_pattern(
+                    pattern,
+                    self.field(),
+                )?))
+            }
+        }
+    };
+}
+
+text_queries!(ExtText);
+text_queries!(ExtFastText);
+
+/// Typed queries for `Tokenized` fields, which are split into lowercased words
+impl ExtField<ExtTokenized> {
+    /// Matches models where one of the words of the field starts with `prefix`
+    pub fn prefix(&self, prefix: &str) -> ExtQuery {
+        ExtQuery::new(FuzzyTermQuery::new_prefix(
+            self.term(prefix.to_lowercase()),
+            0,
+            false,
+        ))
+    }
+
+    /// Matches models where one of the words of the field is within `distance` edits of `word`.
+    /// tantivy supports distances of up to 2
+    pub fn fuzzy(&self, word: &str, distance: u8) -> ExtQuery {
+        ExtQuery::new(FuzzyTermQuery::new(
+            self.term(word.to_lowercase()),
+            distance,
+            true,
+        ))
+    }
+
+    /// Matches models where the words of `phrase` show up in the field in the same order, right next to each other
+    pub fn phrase(&self, phrase: &str) -> ExtQuery {
+        let mut terms = self.tokenize(phrase);
+        match terms.len() {
+            0 => ExtQuery::new(EmptyQuery),
+            1 => ExtQuery::new(TermQuery::new(terms.remove(0), IndexRecordOption::Basic)),
+            _ => ExtQuery::new(PhraseQuery::new(terms)),
+        }
+    }
+
+    /// Matches models where one of the words of the field matches the regular expression.
+    ///
+    /// Words are lowercased when indexed, so the pattern should be too
+    pub fn regex(&self, pattern: &str) -> tantivy::Result<ExtQuery> {
+        Ok(ExtQuery::new(RegexQuery::from_pattern(
+            pattern,
+            self.field(),
+        )?))
+    }
+
+    /// Splits the text the same way the field was split when it got indexed
+    fn tokenize(&self, text: &str) -> Vec<tantivy::Term> {
+        let mut analyzer = TokenizerManager::default()
+            .get("default")
+            .expect("tantivy always registers the default tokenizer");
+        let mut terms = Vec::new();
+        analyzer
+            .token_stream(text)
+            .process(&mut |token| terms.push(self.term(token.text.clone())));
+        terms
+    }
+}
+
+/// Typed queries for `U64` and `FastU64` fields. These are fast fields, so they are matched through their column rather than a term
+impl ExtField<ExtU64> {
+    /// Matches models where the field is exactly `value`
+    pub fn eq(&self, value: u64) -> ExtQuery {
+        self.range(value..=value)
+    }
+
+    /// Matches models where the field is exactly one of the provided values
+    pub fn one_of(&self, values: impl IntoIterator<Item = u64>) -> ExtQuery {
+        ExtQuery::new(BooleanQuery::union(
+            values
+                .into_iter()
+                .map(|value| self.eq(value).boxed())
+                .collect(),
+        ))
+    }
+
+    /// Matches models where the field falls within the range, for example `10..20` or `10..`
+    pub fn range(&self, range: impl RangeBounds<u64>) -> ExtQuery {
+        ExtQuery::new(RangeQuery::new_u64_bounds(
+            self.name(),
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        ))
+    }
+}
+
+/// Typed queries for `F64` and `FastF64` fields. These are fast fields, so they are matched through their column rather than a term
+impl ExtField<ExtF64> {
+    /// Matches models where the field is exactly `value`
+    pub fn eq(&self, value: f64) -> ExtQuery {
+        self.range(value..=value)
+    }
+
+    /// Matches models where the field is exactly one of the provided values
+    pub fn one_of(&self, values: impl IntoIterator<Item = f64>) -> ExtQuery {
+        ExtQuery::new(BooleanQuery::union(
+            values
+                .into_iter()
+                .map(|value| self.eq(value).boxed())
+                .collect(),
+        ))
+    }
+
+    /// Matches models where the field falls within the range, for example `0.5..1.0` or `..=10.0`
+    pub fn range(&self, range: impl RangeBounds<f64>) -> ExtQuery {
+        ExtQuery::new(RangeQuery::new_f64_bounds(
+            self.name(),
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        ))
+    }
+}
+
+/// Typed queries for `I64` and `FastI64` fields. These are fast fields, so they are matched through their column rather than a term
+impl ExtField<ExtI64> {
+    /// Matches models where the field is exactly `value`
+    pub fn eq(&self, value: i64) -> ExtQuery {
+        self.range(value..=value)
+    }
+
+    /// Matches models where the field is exactly one of the provided values
+    pub fn one_of(&self, values: impl IntoIterator<Item = i64>) -> ExtQuery {
+        ExtQuery::new(BooleanQuery::union(
+            values
+                .into_iter()
+                .map(|value| self.eq(value).boxed())
+                .collect(),
+        ))
+    }
+
+    /// Matches models where the field falls within the range, for example `-10..10` or `..0`
+    pub fn range(&self, range: impl RangeBounds<i64>) -> ExtQuery {
+        ExtQuery::new(RangeQuery::new_i64_bounds(
+            self.name(),
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        ))
+    }
+}
+
+/// Typed queries for `Date` fields
+impl ExtField<ExtDate> {
+    /// Matches models where the field is exactly `value`
+    pub fn eq(&self, value: DateTime) -> ExtQuery {
+        ExtQuery::new(TermQuery::new(self.term(value), IndexRecordOption::Basic))
+    }
+
+    /// Matches models where the field is exactly one of the provided values
+    pub fn one_of(&self, values: impl IntoIterator<Item = DateTime>) -> ExtQuery {
+        ExtQuery::new(TermSetQuery::new(
+            values.into_iter().map(|value| self.term(value)),
+        ))
+    }
+
+    /// Matches models where the field falls within the range, for example `start..end` or `cutoff..`
+    pub fn range(&self, range: impl RangeBounds<DateTime>) -> ExtQuery {
+        ExtQuery::new(RangeQuery::new_date_bounds(
+            self.name(),
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        ))
+    }
+}
@@ -1,6 +1,6 @@
 use tantivy::schema::Field;
 
-use super::ext_type_trait::ExtType;
+use super::ext_type_trait::{ExtFastType, ExtNumericType, ExtType};
 
 pub struct ExtText(Field, String);
 impl ExtType for ExtText{
@@ -20,6 +20,44 @@ impl ExtType for ExtText{
     }
 }
 
+/// Like `ExtText`, but for `FastStr` fields, which also keep a column of term ordinals to sort and aggregate by
+pub struct ExtFastText(Field, String);
+impl ExtType for ExtFastText{
+    type Target = String;
+    fn new_from_field(schema:Field, field_name:String)->Self{
+        Self(schema, field_name)
+    }
+    fn name(&self)->String {
+        self.1.clone()
+    }
+    fn field(&self)->&Field {
+        &self.0
+    }
+    fn term(&self,input:Self::Target)->tantivy::Term {
+        let field =  *self.field();
+        tantivy::Term::from_field_text(field, &input)
+    }
+}
+
+/// Like `ExtText`, but for `Tokenized` fields, which are the only ones that keep token positions around
+pub struct ExtTokenized(Field, String);
+impl ExtType for ExtTokenized{
+    type Target = String;
+    fn new_from_field(schema:Field, field_name:String)->Self{
+        Self(schema, field_name)
+    }
+    fn name(&self)->String {
+        self.1.clone()
+    }
+    fn field(&self)->&Field {
+        &self.0
+    }
+    fn term(&self,input:Self::Target)->tantivy::Term {
+        let field =  *self.field();
+        tantivy::Term::from_field_text(field, &input)
+    }
+}
+
 pub struct ExtF64(Field, String);
 impl ExtType for ExtF64{
     type Target = f64;
@@ -38,6 +76,12 @@ impl ExtType for ExtF64{
     }
 }
 
+impl ExtFastType for ExtF64{
+    fn column(readers:&tantivy::fastfield::FastFieldReaders, field_name:&str)->tantivy::Result<Option<tantivy::columnar::Column<Self::Target>>> {
+        readers.column_opt(field_name)
+    }
+}
+
 pub struct ExtU64(Field, String);
 impl ExtType for ExtU64{
     type Target = u64;
@@ -56,6 +100,36 @@ impl ExtType for ExtU64{
     }
 }
 
+impl ExtFastType for ExtU64{
+    fn column(readers:&tantivy::fastfield::FastFieldReaders, field_name:&str)->tantivy::Result<Option<tantivy::columnar::Column<Self::Target>>> {
+        readers.column_opt(field_name)
+    }
+}
+
+pub struct ExtI64(Field, String);
+impl ExtType for ExtI64{
+    type Target = i64;
+    fn new_from_field(schema:Field, field_name:String)->Self{
+        Self(schema,field_name)
+    }
+    fn name(&self)->String {
+        self.1.clone()
+    }
+    fn field(&self)->&Field {
+        &self.0
+    }
+    fn term(&self,input:Self::Target)->tantivy::Term {
+        let field = *self.field();
+        tantivy::Term::from_field_i64(field, input)
+    }
+}
+
+impl ExtFastType for ExtI64{
+    fn column(readers:&tantivy::fastfield::FastFieldReaders, field_name:&str)->tantivy::Result<Option<tantivy::columnar::Column<Self::Target>>> {
+        readers.column_opt(field_name)
+    }
+}
+
 pub struct ExtDate(Field, String);
 impl ExtType for ExtDate{
     type Target = tantivy::DateTime;
@@ -72,4 +146,27 @@ impl ExtType for ExtDate{
         let field = *self.field();
         tantivy::Term::from_field_date(field, input)
     }
-}
\ No newline at end of file
+}
+impl ExtFastType for ExtDate{
+    fn column(readers:&tantivy::fastfield::FastFieldReaders, field_name:&str)->tantivy::Result<Option<tantivy::columnar::Column<Self::Target>>> {
+        readers.column_opt(field_name)
+    }
+}
+
+impl ExtNumericType for ExtF64{
+    fn to_f64(value:Self::Target)->f64 {
+        value
+    }
+}
+
+impl ExtNumericType for ExtU64{
+    fn to_f64(value:Self::Target)->f64 {
+        value as f64
+    }
+}
+
+impl ExtNumericType for ExtI64{
+    fn to_f64(value:Self::Target)->f64 {
+        value as f64
+    }
+}
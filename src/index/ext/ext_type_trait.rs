@@ -7,4 +7,15 @@ pub trait ExtType{
     /** The name of this field */
     fn name(&self)->String;
     fn term(&self,input:Self::Target)->tantivy::Term;
-}
\ No newline at end of file
+}
+
+/** An `ExtType` whose field is a fast field, so its values can be read by document id while searching */
+pub trait ExtFastType: ExtType{
+    /** The column of the field in a segment, or `None` if no document of the segment has a value for it */
+    fn column(readers:&tantivy::fastfield::FastFieldReaders, field_name:&str)->tantivy::Result<Option<tantivy::columnar::Column<Self::Target>>>;
+}
+
+/** A fast `ExtType` holding a number, so its values can feed into scores */
+pub trait ExtNumericType: ExtFastType{
+    fn to_f64(value:Self::Target)->f64;
+}
@@ -1,16 +1,92 @@
-use std::{cell::RefCell, marker::PhantomData, path::PathBuf};
+use std::{cell::RefCell, marker::PhantomData, path::PathBuf, time::Duration};
+
+use tantivy::{store::Compressor, Directory, IndexSettings, Order, ReloadPolicy};
 
 use crate::entity::entity_trait;
 
-use super::search_index::SearchIndex;
+use super::{
+    ext::{ext_field::ExtField, ext_type_trait::ExtType},
+    ingest::IngestPolicy,
+    merge_policy::IndexMergePolicy,
+    read_only::ReadOnlySearchIndexBuilder,
+    recycle_policy::RecyclePolicy,
+    search_index::SearchIndex,
+    writer_recycler::WriterOptions,
+};
+
+/// The smallest memory budget tantivy accepts for each indexing thread.
+///
+/// Mirrors tantivy's `MEMORY_BUDGET_NUM_BYTES_MIN`, which isn't exported.
+pub const MEMORY_BUDGET_PER_THREAD_MIN: usize = 15_000_000;
+
+/// Where the files of an index live
+pub(crate) enum IndexLocation {
+    Path(PathBuf),
+    Directory(Box<dyn Directory>),
+}
+
+/// Everything that `SearchIndexBuilder` can configure
+pub(crate) struct SearchIndexOptions {
+    pub memory_budget_in_bytes: usize,
+    pub num_threads: Option<usize>,
+    pub recycle_policy: RecyclePolicy,
+    pub reload_policy: ReloadPolicy,
+    pub reload_after_commit: bool,
+    pub merge_policy: IndexMergePolicy,
+    pub index_settings: IndexSettings,
+    pub lock_timeout: Duration,
+    pub break_stale_lock: bool,
+    pub queue_capacity: usize,
+    pub ingest_policy: IngestPolicy,
+}
+
+impl Default for SearchIndexOptions {
+    fn default() -> Self {
+        Self {
+            memory_budget_in_bytes: 50_000_000,
+            num_threads: None,
+            recycle_policy: RecyclePolicy::default(),
+            reload_policy: ReloadPolicy::OnCommitWithDelay,
+            reload_after_commit: false,
+            merge_policy: IndexMergePolicy::default(),
+            index_settings: IndexSettings::default(),
+            lock_timeout: Duration::from_secs(10),
+            break_stale_lock: false,
+            queue_capacity: 64,
+            ingest_policy: IngestPolicy::default(),
+        }
+    }
+}
+
+impl SearchIndexOptions {
+    pub fn writer_options(&self) -> WriterOptions {
+        WriterOptions {
+            mem_budget: self.memory_budget_in_bytes,
+            num_threads: self.num_threads,
+            merge_policy: self.merge_policy.clone(),
+            lock_timeout: self.lock_timeout,
+            break_stale_lock: self.break_stale_lock,
+        }
+    }
+}
 
 pub struct SearchIndexBuilder<M>
 where
     M: entity_trait::Index,
 {
     save_path: PathBuf,
+    directory: RefCell<Option<Box<dyn Directory>>>,
     memory_budget_in_bytes: RefCell<usize>,
-    recycle_after: RefCell<usize>,
+    num_threads: RefCell<Option<usize>>,
+    recycle_policy: RefCell<RecyclePolicy>,
+    reload_policy: RefCell<ReloadPolicy>,
+    reload_after_commit: RefCell<bool>,
+    merge_policy: RefCell<IndexMergePolicy>,
+    index_settings: RefCell<IndexSettings>,
+    lock_timeout: RefCell<Duration>,
+    break_stale_lock: RefCell<bool>,
+    queue_capacity: RefCell<usize>,
+    ingest_policy: RefCell<IngestPolicy>,
 
     phantom: PhantomData<M>,
 }
@@ -20,30 +96,218 @@ where
     M: entity_trait::Index,
 {
     pub fn new(save_path: PathBuf) -> Self {
+        let defaults = SearchIndexOptions::default();
         Self {
             save_path,
-            memory_budget_in_bytes: RefCell::new(50_000_000),
-            recycle_after: RefCell::new(1_000_000),
+            directory: RefCell::new(None),
+            memory_budget_in_bytes: RefCell::new(defaults.memory_budget_in_bytes),
+            num_threads: RefCell::new(defaults.num_threads),
+            recycle_policy: RefCell::new(defaults.recycle_policy),
+            reload_policy: RefCell::new(defaults.reload_policy),
+            reload_after_commit: RefCell::new(defaults.reload_after_commit),
+            merge_policy: RefCell::new(defaults.merge_policy),
+            index_settings: RefCell::new(defaults.index_settings),
+            lock_timeout: RefCell::new(defaults.lock_timeout),
+            break_stale_lock: RefCell::new(defaults.break_stale_lock),
+            queue_capacity: RefCell::new(defaults.queue_capacity),
+            ingest_policy: RefCell::new(defaults.ingest_policy),
             phantom: PhantomData,
         }
     }
 
+    /// Keep the index in the provided directory instead of at the save path, for example in a `RamDirectory` for tests.
+    ///
+    /// The index gets created in the directory if it doesn't hold one yet.
+    pub fn with_directory<D>(self, directory: D) -> Self
+    where
+        D: Directory,
+    {
+        *self.directory.borrow_mut() = Some(Box::new(directory));
+        self
+    }
+
     pub fn with_memory_budget(self, memory_budget_in_bytes: usize) -> Self {
         *self.memory_budget_in_bytes.borrow_mut() = memory_budget_in_bytes;
         self
     }
-    
+
+    /// Pin the number of indexing threads instead of letting tantivy pick it.
+    ///
+    /// The memory budget gets split evenly between the threads, and each of them needs at least
+    /// `MEMORY_BUDGET_PER_THREAD_MIN` bytes, otherwise building the index fails.
+    pub fn with_num_threads(self, num_threads: usize) -> Self {
+        *self.num_threads.borrow_mut() = Some(num_threads);
+        self
+    }
+
     /// After how many entries being processed should the `IndexWriter` get recycled
+    ///
+    /// Shorthand for `with_recycle_policy(RecyclePolicy::EntriesProcessed(recycle_after))`
     pub fn with_recycle_after(self, recycle_after: usize) -> Self {
-        *self.recycle_after.borrow_mut() = recycle_after;
+        self.with_recycle_policy(RecyclePolicy::EntriesProcessed(recycle_after))
+    }
+
+    /// When the `IndexWriter` should get recycled. Defaults to every 1,000,000 processed entries
+    pub fn with_recycle_policy(self, recycle_policy: RecyclePolicy) -> Self {
+        *self.recycle_policy.borrow_mut() = recycle_policy;
+        self
+    }
+
+    /// When the `IndexReader` should pick up new commits. Defaults to `ReloadPolicy::OnCommitWithDelay`
+    pub fn with_reload_policy(self, reload_policy: ReloadPolicy) -> Self {
+        *self.reload_policy.borrow_mut() = reload_policy;
         self
     }
 
+    /// If enabled, mutating methods such as `add` only return once their commit is visible to queries.
+    ///
+    /// This is done by reloading the reader right after every commit, regardless of the reload policy.
+    pub fn with_read_your_writes(self, enabled: bool) -> Self {
+        *self.reload_after_commit.borrow_mut() = enabled;
+        self
+    }
+
+    /// How segments should get merged in the background. Defaults to tantivy's `LogMergePolicy`
+    pub fn with_merge_policy(self, merge_policy: IndexMergePolicy) -> Self {
+        *self.merge_policy.borrow_mut() = merge_policy;
+        self
+    }
+
+    /// The settings a new index gets created with.
+    ///
+    /// Settings are stored with the index, so they are ignored when opening an index that already exists.
+    pub fn with_index_settings(self, index_settings: IndexSettings) -> Self {
+        *self.index_settings.borrow_mut() = index_settings;
+        self
+    }
+
+    /// How the doc store of a new index should be compressed.
+    ///
+    /// `Compressor::Zstd` requires the `zstd-compression` feature.
+    pub fn with_docstore_compression(self, compressor: Compressor) -> Self {
+        self.index_settings.borrow_mut().docstore_compression = compressor;
+        self
+    }
+
+    /// The size in bytes of each doc store block that gets compressed and written to disk. Defaults to 16,384
+    pub fn with_docstore_blocksize(self, blocksize: usize) -> Self {
+        self.index_settings.borrow_mut().docstore_blocksize = blocksize;
+        self
+    }
+
+    /// Pre-sort the documents of a new index by the provided fast field.
+    ///
+    /// This is also what `#[tantivy_ext(sort_by = "field", desc)]` on the model struct sets up.
+    ///
+    /// Note that tantivy has deprecated index sorting and plans to remove it in `0.23`.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let index = MyModel::index_builder(path)
+    ///     .with_index_sort(MyModel::popularity_field(), Order::Desc)
+    ///     .build();
+    /// ```
+    #[allow(deprecated)]
+    pub fn with_index_sort<T>(self, field: ExtField<T>, order: Order) -> Self
+    where
+        T: ExtType,
+    {
+        self.index_settings.borrow_mut().sort_by_field = Some(tantivy::IndexSortByField {
+            field: field.name(),
+            order,
+        });
+        self
+    }
+
+    /// How long building waits for another writer, possibly in another process, to release the
+    /// directory lock before giving up. Defaults to 10 seconds
+    ///
+    /// With the default `MmapDirectory`, the lock is released when the process holding it exits, even after a crash.
+    /// Other directories may leave it behind, see `with_stale_lock_recovery`.
+    pub fn with_lock_timeout(self, lock_timeout: Duration) -> Self {
+        *self.lock_timeout.borrow_mut() = lock_timeout;
+        self
+    }
+
+    /// If the directory lock is still busy once the lock timeout runs out, delete the lock file and take it anyway.
+    ///
+    /// Only enable this when the lock is known to be left behind by a process that is gone, for example by a
+    /// `Directory` that doesn't release it when its process crashes. If the process holding the lock is still alive,
+    /// two writers will end up corrupting the index.
+    pub fn with_stale_lock_recovery(self, enabled: bool) -> Self {
+        *self.break_stale_lock.borrow_mut() = enabled;
+        self
+    }
+
+    /// How many write operations can be queued up for the writer task before callers have to wait. Defaults to 64
+    ///
+    /// Operations that are queued up at the same time get applied together and share a single commit.
+    pub fn with_queue_capacity(self, queue_capacity: usize) -> Self {
+        *self.queue_capacity.borrow_mut() = queue_capacity;
+        self
+    }
+
+    /// How `SearchIndex::ingest` batches and commits the models it consumes. Defaults to commits of up to 1000 models,
+    /// made at least every second while models keep trickling in
+    pub fn with_ingest_policy(self, ingest_policy: IngestPolicy) -> Self {
+        *self.ingest_policy.borrow_mut() = ingest_policy;
+        self
+    }
+
+    /// Open the index without a writer instead, for processes that only ever search an index
+    /// that another process writes to.
+    ///
+    /// Only the reload policy carries over, since every other setting concerns the writer.
+    pub fn read_only(self) -> ReadOnlySearchIndexBuilder<M> {
+        let reload_policy = *self.reload_policy.borrow();
+        ReadOnlySearchIndexBuilder::in_location(self.location(), reload_policy)
+    }
+
+    /// Panics if the index can't be opened or created. See `try_build` for the fallible version.
+    ///
+    /// Like `try_build`, this blocks the current thread while waiting for the directory lock. Use `build_async` in async code
     pub fn build(self) -> SearchIndex<M> {
-        SearchIndex::new(
-            self.save_path,
-            *self.memory_budget_in_bytes.borrow(),
-            *self.recycle_after.borrow(),
-        )
+        self.try_build().unwrap()
+    }
+
+    /// Waits with backoff for about the lock timeout if another writer holds the directory lock, sleeping the current
+    /// thread in between attempts.
+    ///
+    /// In async code, use `build_async` instead: called from a tokio task, this stalls the worker thread it runs on,
+    /// along with every other task on it, for as long as the lock stays busy
+    pub fn try_build(self) -> tantivy::Result<SearchIndex<M>> {
+        let location = self.location();
+        SearchIndex::with_options(location, self.options())
+    }
+
+    /// Waits with backoff for up to the lock timeout if another writer holds the directory lock, without blocking the thread.
+    ///
+    /// This is the one to use from async code
+    pub async fn build_async(self) -> tantivy::Result<SearchIndex<M>> {
+        let location = self.location();
+        SearchIndex::with_options_waiting_for_lock(location, self.options()).await
+    }
+
+    fn location(&self) -> IndexLocation {
+        match self.directory.borrow_mut().take() {
+            Some(directory) => IndexLocation::Directory(directory),
+            None => IndexLocation::Path(self.save_path.clone()),
+        }
+    }
+
+    fn options(self) -> SearchIndexOptions {
+        SearchIndexOptions {
+            memory_budget_in_bytes: *self.memory_budget_in_bytes.borrow(),
+            num_threads: *self.num_threads.borrow(),
+            recycle_policy: self.recycle_policy.into_inner(),
+            reload_policy: *self.reload_policy.borrow(),
+            reload_after_commit: *self.reload_after_commit.borrow(),
+            merge_policy: self.merge_policy.into_inner(),
+            index_settings: self.index_settings.into_inner(),
+            lock_timeout: *self.lock_timeout.borrow(),
+            break_stale_lock: *self.break_stale_lock.borrow(),
+            queue_capacity: *self.queue_capacity.borrow(),
+            ingest_policy: self.ingest_policy.into_inner(),
+        }
     }
 }
@@ -0,0 +1,168 @@
+use std::{collections::HashSet, time::Duration};
+
+use futures::{Stream, StreamExt};
+use tantivy::{collector::Count, query::TermSetQuery, Searcher, TantivyError, Term};
+
+use crate::entity::entity_trait;
+
+use super::{
+    transaction::{apply_operations, Operation},
+    writer_actor::WriterActor,
+};
+
+/// How `SearchIndex::ingest` splits a stream of models into commits
+#[derive(Clone, Debug)]
+pub struct IngestPolicy {
+    /// The most models that go into a single commit. A batch always holds at least one model
+    pub batch_size: usize,
+    /// Commit whatever has been gathered once this much time has passed since the first model of the batch arrived,
+    /// even if the batch isn't full yet, so models from slow producers don't sit around uncommitted. Defaults to 1 second.
+    ///
+    /// `None` waits for the batch to fill up or the stream to end, however long that takes
+    pub max_batch_delay: Option<Duration>,
+}
+
+impl Default for IngestPolicy {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            max_batch_delay: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// What happened to the models that went through `SearchIndex::ingest`
+#[derive(Debug, Default)]
+pub struct IngestSummary {
+    /// Models whose primary key wasn't in the index yet
+    pub added: usize,
+    /// Models that replaced a stored model, or an earlier model of the same batch, with the same primary key
+    pub replaced: usize,
+    /// Models that are not in the index because the batch they were in failed
+    pub failed: usize,
+    /// One error for every batch that failed
+    pub errors: Vec<TantivyError>,
+}
+
+impl IngestSummary {
+    fn record(&mut self, batch_len: usize, outcome: tantivy::Result<usize>) {
+        match outcome {
+            Ok(replaced) => {
+                self.added += batch_len - replaced;
+                self.replaced += replaced;
+            }
+            Err(err) => {
+                self.failed += batch_len;
+                self.errors.push(err);
+            }
+        }
+    }
+}
+
+/// Pulls batches off the stream and hands them to `write_batch`.
+///
+/// The next batch gets gathered while the previous one is being committed, but never further ahead than that,
+/// so a producer that outpaces the writer gets slowed down to its pace.
+pub(crate) async fn ingest<M, S, F, Fut>(
+    models: S,
+    policy: &IngestPolicy,
+    mut write_batch: F,
+) -> IngestSummary
+where
+    M: entity_trait::Index,
+    S: Stream<Item = M>,
+    F: FnMut(Vec<Operation>) -> Fut,
+    Fut: std::future::Future<Output = tantivy::Result<usize>>,
+{
+    let mut models = std::pin::pin!(models);
+    let mut summary = IngestSummary::default();
+
+    let mut batch = next_batch(&mut models, policy).await;
+    while !batch.is_empty() {
+        let batch_len = batch.len();
+        let operations = batch
+            .iter()
+            .map(|model| Operation::Add {
+                key: model.get_primary_key(),
+                doc: model.as_document(),
+            })
+            .collect();
+        let (outcome, next) =
+            tokio::join!(write_batch(operations), next_batch(&mut models, policy));
+        summary.record(batch_len, outcome);
+        batch = next;
+    }
+    summary
+}
+
+/// Waits for the first model, then keeps gathering until the batch is full, the stream ends or the batch delay runs out
+async fn next_batch<M, S>(models: &mut S, policy: &IngestPolicy) -> Vec<M>
+where
+    S: Stream<Item = M> + Unpin,
+{
+    let mut batch = Vec::new();
+    let Some(first) = models.next().await else {
+        return batch;
+    };
+    batch.push(first);
+
+    let deadline = policy
+        .max_batch_delay
+        .map(|delay| tokio::time::Instant::now() + delay);
+    while batch.len() < policy.batch_size {
+        let next = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, models.next()).await {
+                Ok(next) => next,
+                Err(_) => break,
+            },
+            None => models.next().await,
+        };
+        match next {
+            Some(model) => batch.push(model),
+            None => break,
+        }
+    }
+    batch
+}
+
+/// Applies and commits one batch of adds on the writer task, rolling it back if any of them or the commit fail.
+///
+/// Returns how many of the adds replaced a model with the same primary key
+pub(crate) fn write_batch(
+    actor: &mut WriterActor,
+    operations: Vec<Operation>,
+) -> tantivy::Result<usize> {
+    let replaced = count_replaced(&actor.latest_searcher()?, &operations)?;
+    let entries = operations.len();
+    let bytes = operations.iter().map(Operation::estimated_size).sum();
+
+    if let Err(err) = actor
+        .writer()
+        .and_then(|writer| apply_operations(writer, operations))
+    {
+        actor.rollback()?;
+        return Err(err);
+    }
+    // A commit that fails rolls the batch back itself, so none of it leaks into the next batch
+    actor.commit()?;
+    actor.recycler.register_entries_processed(entries, bytes)?;
+    Ok(replaced)
+}
+
+/// Counts the adds whose primary key is already stored, with a single query for the whole batch
+fn count_replaced(searcher: &Searcher, operations: &[Operation]) -> tantivy::Result<usize> {
+    let keys: Vec<&Term> = operations
+        .iter()
+        .filter_map(|operation| match operation {
+            Operation::Add { key, .. } => Some(key),
+            _ => None,
+        })
+        .collect();
+    let distinct: HashSet<&Term> = keys.iter().copied().collect();
+    // A key that shows up more than once in the same batch replaces its earlier occurrences
+    let repeated = keys.len() - distinct.len();
+    // Primary keys are unique, so every stored model that matches is replaced by exactly one add
+    let query = TermSetQuery::new(distinct.into_iter().cloned());
+    let stored = searcher.search(&query, &Count)?;
+    Ok(repeated + stored)
+}
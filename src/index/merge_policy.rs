@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use tantivy::{
+    indexer::{LogMergePolicy, MergeCandidate, MergePolicy, NoMergePolicy},
+    SegmentMeta,
+};
+
+/// How the `IndexWriter` should merge segments in the background.
+///
+/// Because the `IndexWriter` gets recycled, the policy is applied again to every new writer.
+#[derive(Clone, Debug)]
+pub enum IndexMergePolicy {
+    /// tantivy's `LogMergePolicy` with the provided parameters. This is the default
+    LogMerge(LogMergePolicy),
+    /// Never merge segments. Useful for bulk loads that get followed by `SearchIndex::optimize`
+    NoMerge,
+    /// Any other merge policy
+    Custom(Arc<dyn MergePolicy>),
+}
+
+impl Default for IndexMergePolicy {
+    fn default() -> Self {
+        Self::LogMerge(LogMergePolicy::default())
+    }
+}
+
+impl IndexMergePolicy {
+    pub(crate) fn to_tantivy(&self) -> Box<dyn MergePolicy> {
+        match self {
+            Self::LogMerge(policy) => Box::new(policy.clone()),
+            Self::NoMerge => Box::new(NoMergePolicy),
+            Self::Custom(policy) => Box::new(SharedMergePolicy(Arc::clone(policy))),
+        }
+    }
+}
+
+/// Lets several writers share the same custom merge policy
+#[derive(Debug)]
+struct SharedMergePolicy(Arc<dyn MergePolicy>);
+
+impl MergePolicy for SharedMergePolicy {
+    fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        self.0.compute_merge_candidates(segments)
+    }
+}
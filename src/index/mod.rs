@@ -1,12 +1,32 @@
 pub mod index_builder;
-mod query{
+pub mod query{
     pub mod builder;
+    pub mod bool_query;
+    pub mod cursor;
+    pub mod parser;
+    pub mod results;
+    pub mod score;
+    pub mod score_functions;
+    pub mod sort;
+    mod minimum_should_match;
+    mod search_after;
 }
 pub mod ext{
     pub mod ext_field;
     pub mod ext_type_trait;
     pub mod ext_type;
+    pub mod ext_query;
 }
 mod backend;
+mod blocking;
+mod writer_actor;
 mod writer_recycler;
-pub mod search_index;
\ No newline at end of file
+pub mod merge_policy;
+pub mod recycle_policy;
+pub mod read_only;
+pub mod searchable;
+pub mod search_index;
+pub mod transaction;
+pub mod patch;
+pub mod ingest;
+pub mod aggregation;
\ No newline at end of file
@@ -0,0 +1,88 @@
+use std::marker::PhantomData;
+
+use tantivy::{
+    schema::{Field, OwnedValue},
+    TantivyDocument, Term,
+};
+
+use crate::entity::entity_trait;
+
+use super::{
+    ext::{ext_field::ExtField, ext_type_trait::ExtType},
+    search_index::SearchIndex,
+};
+
+/// A set of changes to specific fields of a single stored model.
+///
+/// When applied, the stored document is loaded, the patched fields are replaced and the document
+/// is re-added. If another write changes the model in the meantime, the patch gets applied to the fresh copy instead.
+/// Fields that aren't patched keep their stored values.
+#[must_use = "a patch does nothing unless `apply` is called"]
+pub struct Patch<'a, M>
+where
+    M: entity_trait::Index,
+{
+    index: &'a SearchIndex<M>,
+    key: Term,
+    changes: Vec<(Field, OwnedValue)>,
+
+    phantom: PhantomData<M>,
+}
+
+impl<'a, M> Patch<'a, M>
+where
+    M: entity_trait::Index,
+{
+    pub(crate) fn new(index: &'a SearchIndex<M>, key: Term) -> Self {
+        Self {
+            index,
+            key,
+            changes: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Replace the value of the provided field
+    pub fn set<T>(mut self, field: ExtField<T>, value: T::Target) -> Self
+    where
+        T: ExtType,
+        T::Target: Into<OwnedValue>,
+    {
+        let field = field.field();
+        self.changes.retain(|(existing, _)| *existing != field);
+        self.changes.push((field, value.into()));
+        self
+    }
+
+    /// Applies the changes and commits them.
+    ///
+    /// Returns `false` if there is no model with the primary key of this patch.
+    pub async fn apply(self) -> tantivy::Result<bool> {
+        let index = self.index;
+        let key = self.key.clone();
+        let applied = index
+            .writer
+            .replace_by_key(key, move |score, stored| {
+                let doc = self.patched(stored);
+                // The patch may have changed the primary key, so both the old and new one get cleared
+                let new_key = M::from_document(doc.clone(), score).get_primary_key();
+                ((), new_key, doc)
+            })
+            .await?;
+        Ok(applied.is_some())
+    }
+
+    fn patched(&self, stored: TantivyDocument) -> TantivyDocument {
+        let mut doc = TantivyDocument::new();
+        for field_value in stored.field_values() {
+            let field = field_value.field();
+            if !self.changes.iter().any(|(changed, _)| *changed == field) {
+                doc.add_field_value(field, field_value.value().clone());
+            }
+        }
+        for (field, value) in &self.changes {
+            doc.add_field_value(*field, value.clone());
+        }
+        doc
+    }
+}
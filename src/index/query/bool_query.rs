@@ -0,0 +1,147 @@
+use tantivy::{
+    query::{
+        AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, EnableScoring, Occur, Query, Weight,
+    },
+    Term,
+};
+
+use crate::index::ext::ext_query::ExtQuery;
+
+use super::minimum_should_match::MinimumShouldMatchQuery;
+
+/// Combines queries, such as the ones built from `ExtField` handles, into a single boolean query.
+///
+/// Can be passed to `SearchIndex::query` directly, or turned into an `ExtQuery` with `build` to keep combining it.
+///
+/// Example:
+/// ```ignore
+/// let query = BoolQueryBuilder::new()
+///     .must(MyModel::path_field().phrase("annual report"))
+///     .should(MyModel::name_field().prefix("2024"))
+///     .should(MyModel::name_field().prefix("2025"))
+///     .minimum_should_match(1)
+///     .filter(MyModel::date_field().range(cutoff..))
+///     .must_not(MyModel::name_field().eq("draft"));
+/// let results = index.query(&query, 10).execute()?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BoolQueryBuilder {
+    must: Vec<ExtQuery>,
+    should: Vec<ExtQuery>,
+    must_not: Vec<ExtQuery>,
+    filter: Vec<ExtQuery>,
+    minimum_should_match: Option<usize>,
+    boost: Option<f32>,
+}
+
+impl BoolQueryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Has to match, and adds to the score
+    pub fn must<Q>(mut self, query: Q) -> Self
+    where
+        Q: Query,
+    {
+        self.must.push(ExtQuery::new(query));
+        self
+    }
+
+    /// Adds to the score if it matches. Without any `must` or `filter` clauses, at least one of these has to match
+    pub fn should<Q>(mut self, query: Q) -> Self
+    where
+        Q: Query,
+    {
+        self.should.push(ExtQuery::new(query));
+        self
+    }
+
+    /// Must not match. A builder with nothing but these clauses matches every model the clauses don't
+    pub fn must_not<Q>(mut self, query: Q) -> Self
+    where
+        Q: Query,
+    {
+        self.must_not.push(ExtQuery::new(query));
+        self
+    }
+
+    /// Has to match, but doesn't affect the score
+    pub fn filter<Q>(mut self, query: Q) -> Self
+    where
+        Q: Query,
+    {
+        self.filter.push(ExtQuery::new(query));
+        self
+    }
+
+    /// How many of the `should` clauses have to match, even when there are `must` or `filter` clauses
+    pub fn minimum_should_match(mut self, minimum: usize) -> Self {
+        self.minimum_should_match = Some(minimum);
+        self
+    }
+
+    /// Multiplies the score of every match by `boost`
+    pub fn boost(mut self, boost: f32) -> Self {
+        self.boost = Some(boost);
+        self
+    }
+
+    pub fn build(&self) -> ExtQuery {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for query in &self.must {
+            clauses.push((Occur::Must, query.clone().boxed()));
+        }
+        for query in &self.filter {
+            clauses.push((
+                Occur::Must,
+                Box::new(ConstScoreQuery::new(query.clone().boxed(), 0.0)),
+            ));
+        }
+        let should = self.should.iter().map(|query| query.clone().boxed());
+        match self.minimum_should_match {
+            Some(minimum) if minimum > 0 && !self.should.is_empty() => clauses.push((
+                Occur::Must,
+                Box::new(MinimumShouldMatchQuery::new(should.collect(), minimum)),
+            )),
+            _ => clauses.extend(should.map(|query| (Occur::Should, query))),
+        }
+        if clauses.is_empty() && !self.must_not.is_empty() {
+            // A boolean query with nothing but exclusions matches nothing, so everything has to be included first
+            clauses.push((Occur::Must, Box::new(AllQuery)));
+        }
+        for query in &self.must_not {
+            clauses.push((Occur::MustNot, query.clone().boxed()));
+        }
+
+        let query = BooleanQuery::new(clauses);
+        match self.boost {
+            Some(boost) => ExtQuery::new(BoostQuery::new(Box::new(query), boost)),
+            None => ExtQuery::new(query),
+        }
+    }
+}
+
+impl Query for BoolQueryBuilder {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        self.build().weight(enable_scoring)
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for query in self
+            .must
+            .iter()
+            .chain(&self.should)
+            .chain(&self.must_not)
+            .chain(&self.filter)
+        {
+            query.query_terms(visitor);
+        }
+    }
+}
+
+impl From<BoolQueryBuilder> for ExtQuery {
+    fn from(builder: BoolQueryBuilder) -> Self {
+        builder.build()
+    }
+}
@@ -1,8 +1,33 @@
-use std::marker::PhantomData;
+use std::{fmt::Debug, marker::PhantomData, sync::Arc, time::Instant};
 
-use tantivy::{collector::TopDocs, query::Query, Searcher};
+use tantivy::{
+    collector::{Collector, Count, TopDocs},
+    query::Query,
+    schema::Field,
+    snippet::SnippetGenerator,
+    DocAddress, DocId, Order, Score, Searcher, SegmentReader, TantivyError,
+};
 
-use crate::entity::entity_trait;
+use crate::{
+    entity::entity_trait,
+    index::{
+        blocking,
+        ext::{
+            ext_field::ExtField,
+            ext_type::{ExtDate, ExtTokenized},
+            ext_type_trait::ExtNumericType,
+        },
+    },
+};
+
+use super::{
+    cursor::{Cursor, Page, PinnedSearchers, Position},
+    results::{Highlight, Hit, SearchResults},
+    score::{FastFieldContext, ScoreTweak, SegmentTweak},
+    score_functions::{Decay, FieldValueFactor, Modifier},
+    search_after::{SearchAfter, SegmentRanker},
+    sort::{ExtSortType, KeyValue, SortColumn, SortField, SortKey, SortValue},
+};
 
 pub struct QueryBuilder<'a, Q, M>
 where
@@ -12,6 +37,12 @@ where
     query: &'a Q,
     searcher: Searcher,
     max_results: usize,
+    tweaks: Vec<ScoreTweak>,
+    sort: Vec<SortField>,
+    offset: usize,
+    after: Option<Cursor>,
+    highlights: Vec<(Field, usize)>,
+    pinned: PinnedSearchers,
     phantom: PhantomData<M>,
 }
 
@@ -25,23 +56,565 @@ where
             query,
             searcher,
             max_results,
+            tweaks: Vec::new(),
+            sort: Vec::new(),
+            offset: 0,
+            after: None,
+            highlights: Vec::new(),
+            pinned: PinnedSearchers::default(),
             phantom: PhantomData,
         }
     }
 
-    // TODO: add a score tweak feature
+    /// Keep the searchers that cursors refer to in `pinned`, which outlives the builder
+    pub(crate) fn with_pinned(mut self, pinned: PinnedSearchers) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    /// Rewrite the score of every match, for example to combine relevance with a fast field.
+    ///
+    /// `tweak` gets called once for every segment, to open the columns it needs,
+    /// and returns the function that rewrites the score of each document of that segment.
+    /// If it fails, so does the search.
+    ///
+    /// Results are ranked by the tweaked score. Calling this again tweaks the output of the previous tweak.
+    ///
+    /// Example:
+    /// ```ignore
+    /// index
+    ///     .query(&query, 10)
+    ///     .tweak_score(|ctx| {
+    ///         let popularity = ctx.fast(MyModel::popularity_field())?;
+    ///         Ok(move |doc, score| {
+    ///             score * (1.0 + popularity.first(doc).unwrap_or(0.0) as f32).ln()
+    ///         })
+    ///     })
+    ///     .execute()?;
+    /// ```
+    pub fn tweak_score<F, G>(mut self, tweak: F) -> Self
+    where
+        F: Fn(&FastFieldContext) -> tantivy::Result<G> + Send + Sync + 'static,
+        G: Fn(DocId, Score) -> Score + 'static,
+    {
+        self.tweaks.push(Arc::new(move |ctx| {
+            Ok(Box::new(tweak(ctx)?) as SegmentTweak)
+        }));
+        self
+    }
+
+    /// Multiply the score of every match by how close its date is to the origin of the decay, for example to favour recent models.
+    /// Matches without a date keep their score.
+    ///
+    /// Like every scoring function, this runs after the tweaks and scoring functions that were added before it.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let week = Duration::from_secs(7 * 24 * 60 * 60);
+    /// index
+    ///     .query(&query, 10)
+    ///     .decay(MyModel::date_field(), Decay::exponential(now, week).offset(week))
+    ///     .log_boost(MyModel::popularity_field(), 1.0)
+    ///     .execute()?;
+    /// ```
+    pub fn decay(self, field: ExtField<ExtDate>, decay: Decay) -> Self {
+        let name = field.name();
+        let field = field.field();
+        self.tweak_score(move |ctx| {
+            let dates = ctx.column::<ExtDate>(field, &name)?;
+            let decay = decay.clone();
+            Ok(move |doc, score| match dates.first(doc) {
+                Some(date) => (score as f64 * decay.factor(date)) as Score,
+                None => score,
+            })
+        })
+    }
+
+    /// Multiply the score of every match by the value of a numeric fast field, as described by the `FieldValueFactor`
+    pub fn field_value_factor<T>(self, field: ExtField<T>, factor: FieldValueFactor) -> Self
+    where
+        T: ExtNumericType,
+        T::Target: PartialOrd + Copy + Debug + Send + Sync + 'static,
+    {
+        let name = field.name();
+        let field = field.field();
+        self.tweak_score(move |ctx| {
+            let values = ctx.column::<T>(field, &name)?;
+            let factor = factor.clone();
+            Ok(move |doc, score| {
+                let value = values.first(doc).map(T::to_f64);
+                (score as f64 * factor.value_factor(value)) as Score
+            })
+        })
+    }
+
+    /// Multiply the score of every match by `ln(2 + factor * value)`, so large values help without drowning out relevance
+    pub fn log_boost<T>(self, field: ExtField<T>, factor: f64) -> Self
+    where
+        T: ExtNumericType,
+        T::Target: PartialOrd + Copy + Debug + Send + Sync + 'static,
+    {
+        self.field_value_factor(
+            field,
+            FieldValueFactor::new()
+                .factor(factor)
+                .modifier(Modifier::Ln2p),
+        )
+    }
+
+    /// Order the results by a fast field instead of by score. Calling this again adds a tie-breaker
+    /// for models that have the same value for every field before it, and the score breaks whatever ties are left.
+    ///
+    /// Works with `FastU64`, `FastI64`, `FastF64`, `FastStr` and `Date` fields. Models without a value for the field come last.
+    /// If the model has a `SortValues` field, it gets filled with the values the model was sorted by.
+    ///
+    /// Example:
+    /// ```ignore
+    /// index
+    ///     .query(&query, 10)
+    ///     .order_by(MyModel::date_field(), Order::Desc)
+    ///     .order_by(MyModel::name_field(), Order::Asc)
+    ///     .execute()?;
+    /// ```
+    pub fn order_by<T>(mut self, field: ExtField<T>, order: Order) -> Self
+    where
+        T: ExtSortType,
+    {
+        self.sort.push(SortField::new(field, order));
+        self
+    }
+
+    /// Skip the first `offset` results.
+    ///
+    /// The skipped results still have to be ranked, so deep pages get slower. Prefer `after` for those
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Continue from where the page that returned the cursor ended.
+    ///
+    /// The search reads from the generation of the index the cursor refers to, and has to be ordered the same way
+    /// as the search that returned it. Otherwise, or once the cursor expired, it fails with `TantivyError::InvalidArgument`
+    pub fn after(mut self, cursor: Cursor) -> Self {
+        self.after = Some(cursor);
+        self
+    }
+
+    /// Pick out the part of a `Tokenized` field that best matches the query, of at most `max_chars` characters,
+    /// for every hit of `execute_results`. Call it once for every field to highlight.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let results = index
+    ///     .query(&query, 10)
+    ///     .highlight(MyModel::path_field(), 150)
+    ///     .execute_results()?;
+    /// for hit in results.hits {
+    ///     println!("{}", hit.highlights[0].html);
+    /// }
+    /// ```
+    pub fn highlight(mut self, field: ExtField<ExtTokenized>, max_chars: usize) -> Self {
+        self.highlights.push((field.field(), max_chars));
+        self
+    }
 
     pub fn execute(self) -> tantivy::Result<Vec<M>> {
-        let documents = self
-            .searcher
-            .search(self.query, &TopDocs::with_limit(self.max_results))?;
-        let models: Vec<M> = documents
-            .into_iter()
-            .map(|(score, address)| {
-                let doc = self.searcher.doc(address).unwrap();
-                M::from_document(doc, score)
+        let (searcher, plan) = self.plan(Output::Models)?;
+        search(&searcher, self.query, plan).map(SearchResults::into_models)
+    }
+
+    /// Like `execute`, but also returns the cursor to the next page
+    pub fn execute_page(self) -> tantivy::Result<Page<M>> {
+        let (searcher, plan) = self.plan(Output::Page)?;
+        search(&searcher, self.query, plan).map(Page::from)
+    }
+
+    /// Like `execute_page`, but every model comes with its score, address and sort values,
+    /// along with how many models match in total and how long the search took.
+    ///
+    /// Counting every match takes a little longer than just finding the best ones
+    pub fn execute_results(self) -> tantivy::Result<SearchResults<M>> {
+        let (searcher, plan) = self.plan(Output::Results)?;
+        search(&searcher, self.query, plan)
+    }
+
+    /// Like `execute`, but the search and the loading of the documents run on tokio's blocking thread pool
+    pub async fn execute_async(self) -> tantivy::Result<Vec<M>>
+    where
+        M: Send + 'static,
+    {
+        self.search_async(Output::Models)
+            .await
+            .map(SearchResults::into_models)
+    }
+
+    /// Like `execute_page`, but the search and the loading of the documents run on tokio's blocking thread pool
+    pub async fn execute_page_async(self) -> tantivy::Result<Page<M>>
+    where
+        M: Send + 'static,
+    {
+        self.search_async(Output::Page).await.map(Page::from)
+    }
+
+    /// Like `execute_results`, but the search and the loading of the documents run on tokio's blocking thread pool
+    pub async fn execute_results_async(self) -> tantivy::Result<SearchResults<M>>
+    where
+        M: Send + 'static,
+    {
+        self.search_async(Output::Results).await
+    }
+
+    async fn search_async(self, output: Output) -> tantivy::Result<SearchResults<M>>
+    where
+        M: Send + 'static,
+    {
+        let (searcher, plan) = self.plan(output)?;
+        let query = self.query.box_clone();
+        blocking::run(move || search(&searcher, query.as_ref(), plan)).await
+    }
+
+    /// The searcher to read from, which is the one the cursor refers to if there is one, and the plan of the search
+    fn plan(&self, output: Output) -> tantivy::Result<(Searcher, Plan)> {
+        let searcher = match &self.after {
+            Some(cursor) => self.pinned.get(cursor)?,
+            None => self.searcher.clone(),
+        };
+        let plan = Plan {
+            max_results: self.max_results,
+            offset: self.offset,
+            tweaks: self.tweaks.clone(),
+            sort: self.sort.clone(),
+            after: self
+                .after
+                .as_ref()
+                .map(|cursor| (cursor.position().clone(), cursor.address())),
+            count: output == Output::Results,
+            // Snippet generators look up the terms of the query up front, which is wasted when nobody reads the highlights
+            highlights: match output {
+                Output::Results => self.highlights.clone(),
+                _ => Vec::new(),
+            },
+            // A pinned searcher that no cursor refers to would only push out the ones that cursors do refer to
+            pinned: match output {
+                Output::Models => None,
+                _ => Some(self.pinned.clone()),
+            },
+        };
+        Ok((searcher, plan))
+    }
+}
+
+/// What the caller of a search gets to see
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Output {
+    /// Just the models, as with `execute`
+    Models,
+    /// The models and the cursor to the next page, as with `execute_page`
+    Page,
+    /// Everything `execute_results` returns
+    Results,
+}
+
+/// Everything about a search besides the query and the searcher
+struct Plan {
+    max_results: usize,
+    offset: usize,
+    tweaks: Vec<ScoreTweak>,
+    sort: Vec<SortField>,
+    after: Option<(Position, DocAddress)>,
+    /// Whether to count every match on top of finding the best ones
+    count: bool,
+    /// The fields to highlight and how many characters to pick out of them. Empty unless the search is for `execute_results`
+    highlights: Vec<(Field, usize)>,
+    /// Where the searcher goes for the cursor of the next page to refer to. `None` if the caller never sees the cursor
+    pinned: Option<PinnedSearchers>,
+}
+
+impl Plan {
+    /// The name and order of every `order_by` field
+    fn sort_fields(&self) -> Vec<(String, Order)> {
+        self.sort
+            .iter()
+            .map(|field| (field.name().to_string(), field.order.clone()))
+            .collect()
+    }
+}
+
+fn search<M>(
+    searcher: &Searcher,
+    query: &dyn Query,
+    plan: Plan,
+) -> tantivy::Result<SearchResults<M>>
+where
+    M: entity_trait::Index,
+{
+    let started = Instant::now();
+    let max_results = plan.max_results;
+    let sort = plan.sort_fields();
+    let pinned = plan.pinned.clone();
+    let snippets = plan
+        .highlights
+        .iter()
+        .map(|(field, max_chars)| {
+            let mut generator = SnippetGenerator::create(searcher, query, *field)?;
+            generator.set_max_num_chars(*max_chars);
+            Ok((
+                searcher.schema().get_field_name(*field).to_string(),
+                generator,
+            ))
+        })
+        .collect::<tantivy::Result<Vec<_>>>()?;
+    let (total_hits, documents) = if plan.sort.is_empty() {
+        top_by_score(searcher, query, plan)?
+    } else {
+        top_by_fields(searcher, query, plan)?
+    };
+    let next = match (documents.last(), pinned) {
+        (Some((score, values, address)), Some(pinned)) if documents.len() == max_results => {
+            let position = if sort.is_empty() {
+                Position::Score(*score)
+            } else {
+                Position::Fields {
+                    sort,
+                    values: values.clone(),
+                    score: *score,
+                }
+            };
+            pinned.pin(searcher);
+            let generation = searcher.generation().generation_id();
+            Some(Cursor::new(generation, position, *address))
+        }
+        _ => None,
+    };
+    let hits = documents
+        .into_iter()
+        .map(|(score, sort_values, address)| {
+            let doc = searcher.doc(address)?;
+            let highlights = snippets
+                .iter()
+                .map(|(field, generator)| {
+                    let snippet = generator.snippet_from_doc(&doc);
+                    Highlight {
+                        field: field.clone(),
+                        fragment: snippet.fragment().to_string(),
+                        ranges: snippet.highlighted().to_vec(),
+                        html: snippet.to_html(),
+                    }
+                })
+                .collect();
+            let mut model = M::from_document(doc, score);
+            if !sort_values.is_empty() {
+                model.set_sort_values(sort_values.clone().into());
+            }
+            Ok(Hit {
+                model,
+                score,
+                address,
+                sort_values,
+                highlights,
+            })
+        })
+        .collect::<tantivy::Result<_>>()?;
+    Ok(SearchResults {
+        total_hits,
+        hits,
+        elapsed: started.elapsed(),
+        next,
+    })
+}
+
+/// The number of matches, if the plan asked for it, and the best of them in order with their score and sort values
+type Found = (usize, Vec<(Score, Vec<SortValue>, DocAddress)>);
+
+fn top_by_score(searcher: &Searcher, query: &dyn Query, plan: Plan) -> tantivy::Result<Found> {
+    let after = match plan.after {
+        None => None,
+        Some((Position::Score(score), address)) => Some((score, address)),
+        Some(_) => return Err(ordered_differently()),
+    };
+    let (total_hits, documents) = if plan.tweaks.is_empty() && after.is_none() {
+        let collector = TopDocs::with_limit(plan.max_results).and_offset(plan.offset);
+        collect(searcher, query, collector, plan.count)?
+    } else {
+        let tweaks = plan.tweaks;
+        top(
+            searcher,
+            query,
+            plan.max_results,
+            plan.offset,
+            after,
+            plan.count,
+            move |segment: &SegmentReader| {
+                Ok(ScoreRanker {
+                    tweaks: open_tweaks(&tweaks, &FastFieldContext::new(segment))?,
+                })
+            },
+        )?
+    };
+    let documents = documents
+        .into_iter()
+        .map(|(score, address)| (score, Vec::new(), address))
+        .collect();
+    Ok((total_hits, documents))
+}
+
+fn top_by_fields(searcher: &Searcher, query: &dyn Query, plan: Plan) -> tantivy::Result<Found> {
+    let orders: Arc<[Order]> = plan.sort.iter().map(|field| field.order.clone()).collect();
+    let after = match plan.after {
+        None => None,
+        Some((
+            Position::Fields {
+                sort,
+                values,
+                score,
+            },
+            address,
+        )) if sort == plan.sort_fields() => {
+            let values = values.into_iter().map(KeyValue::Value).collect();
+            Some((SortKey::new(values, score, orders.clone()), address))
+        }
+        Some(_) => return Err(ordered_differently()),
+    };
+    let tweaks = plan.tweaks;
+    let sort = plan.sort;
+    let (total_hits, documents) = top(
+        searcher,
+        query,
+        plan.max_results,
+        plan.offset,
+        after,
+        plan.count,
+        move |segment: &SegmentReader| {
+            let ctx = FastFieldContext::new(segment);
+            Ok(FieldRanker {
+                tweaks: open_tweaks(&tweaks, &ctx)?,
+                columns: sort
+                    .iter()
+                    .map(|field| field.open(&ctx))
+                    .collect::<tantivy::Result<_>>()?,
+                orders: orders.clone(),
             })
+        },
+    )?;
+    let documents = documents
+        .into_iter()
+        .map(|(key, address)| (key.score, key.sort_values(), address))
+        .collect();
+    Ok((total_hits, documents))
+}
+
+/// Ranks every match by the key the ranker of its segment gives it, keeping only the ones that come after `after` if it is set
+fn top<K, F, R>(
+    searcher: &Searcher,
+    query: &dyn Query,
+    limit: usize,
+    offset: usize,
+    after: Option<(K, DocAddress)>,
+    count: bool,
+    ranker_for_segment: F,
+) -> tantivy::Result<(usize, Vec<(K, DocAddress)>)>
+where
+    K: PartialOrd + Clone + Send + Sync + 'static,
+    F: Fn(&SegmentReader) -> tantivy::Result<R> + Send + Sync + 'static,
+    R: SegmentRanker<Key = K>,
+{
+    let collector = SearchAfter::new(after, limit, offset, ranker_for_segment);
+    collect(searcher, query, collector, count)
+}
+
+/// Runs the collector, counting every match alongside it when `count` is set. Otherwise the count is 0
+fn collect<C>(
+    searcher: &Searcher,
+    query: &dyn Query,
+    collector: C,
+    count: bool,
+) -> tantivy::Result<(usize, C::Fruit)>
+where
+    C: Collector,
+{
+    if count {
+        searcher.search(query, &(Count, collector))
+    } else {
+        Ok((0, searcher.search(query, &collector)?))
+    }
+}
+
+fn ordered_differently() -> TantivyError {
+    TantivyError::InvalidArgument(
+        "The cursor comes from a search that was ordered differently".to_string(),
+    )
+}
+
+fn open_tweaks(
+    tweaks: &[ScoreTweak],
+    ctx: &FastFieldContext,
+) -> tantivy::Result<Vec<SegmentTweak>> {
+    tweaks.iter().map(|tweak| tweak(ctx)).collect()
+}
+
+fn apply_tweaks(tweaks: &[SegmentTweak], doc: DocId, score: Score) -> Score {
+    tweaks.iter().fold(score, |score, tweak| tweak(doc, score))
+}
+
+/// Ranks the matches of a segment by their tweaked score
+struct ScoreRanker {
+    tweaks: Vec<SegmentTweak>,
+}
+
+impl SegmentRanker for ScoreRanker {
+    type Key = Score;
+
+    fn key(&mut self, doc: DocId, score: Score) -> Score {
+        apply_tweaks(&self.tweaks, doc, score)
+    }
+
+    fn local(&self, score: &Score) -> tantivy::Result<Score> {
+        Ok(*score)
+    }
+
+    fn global(&self, score: Score) -> Score {
+        score
+    }
+}
+
+/// Ranks the matches of a segment by the fields of an `order_by` chain, and then by their tweaked score
+struct FieldRanker {
+    tweaks: Vec<SegmentTweak>,
+    columns: Vec<SortColumn>,
+    orders: Arc<[Order]>,
+}
+
+impl SegmentRanker for FieldRanker {
+    type Key = SortKey;
+
+    fn key(&mut self, doc: DocId, score: Score) -> SortKey {
+        let score = apply_tweaks(&self.tweaks, doc, score);
+        let values = self
+            .columns
+            .iter()
+            .map(|column| column.value(doc))
+            .collect();
+        SortKey::new(values, score, self.orders.clone())
+    }
+
+    fn local(&self, key: &SortKey) -> tantivy::Result<SortKey> {
+        let values = self
+            .columns
+            .iter()
+            .zip(&key.values)
+            .map(|(column, value)| column.local(value))
+            .collect::<tantivy::Result<_>>()?;
+        Ok(SortKey::new(values, key.score, self.orders.clone()))
+    }
+
+    fn global(&self, key: SortKey) -> SortKey {
+        let values = self
+            .columns
+            .iter()
+            .zip(key.values)
+            .map(|(column, value)| column.global(value))
             .collect();
-        Ok(models)
+        SortKey::new(values, key.score, self.orders.clone())
     }
 }
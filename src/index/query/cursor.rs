@@ -0,0 +1,148 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tantivy::{DocAddress, Order, Score, Searcher, TantivyError};
+
+use super::{results::SearchResults, sort::SortValue};
+
+/// How many generations of the index cursors can refer to at once
+const PINNED_SEARCHERS: usize = 8;
+
+/// Where a page of results ended, so the next page can pick up right after it with `QueryBuilder::after`.
+///
+/// A cursor refers to the generation of the index its page was read from, and every page it leads to
+/// is read from that same generation. Commits made in the meantime don't shift results between pages,
+/// but they also don't show up until a search starts over without a cursor.
+///
+/// The index keeps the searchers of the last 8 generations that cursors were used with around.
+/// Once the generation of a cursor is dropped from those, the cursor has expired and searching after it
+/// fails with `TantivyError::InvalidArgument`, so the search has to start over.
+///
+/// Cursors can be handed to clients as a token with `encode`, and turned back into a cursor with `decode`.
+///
+/// Example:
+/// ```ignore
+/// let mut page = index.query(&query, 20).execute_page()?;
+/// while let Some(cursor) = page.next {
+///     page = index.query(&query, 20).after(cursor).execute_page()?;
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    generation: u64,
+    position: Position,
+    address: DocAddress,
+}
+
+/// What the last hit of a page was ranked by
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Position {
+    Score(Score),
+    Fields {
+        /// The name and order of every `order_by` field, so the cursor can't be used with a search ordered differently
+        sort: Vec<(String, Order)>,
+        values: Vec<SortValue>,
+        score: Score,
+    },
+}
+
+impl Cursor {
+    pub(crate) fn new(generation: u64, position: Position, address: DocAddress) -> Self {
+        Self {
+            generation,
+            position,
+            address,
+        }
+    }
+
+    /// The generation of the index the cursor refers to
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub(crate) fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub(crate) fn address(&self) -> DocAddress {
+        self.address
+    }
+
+    /// An opaque token that `decode` turns back into the cursor, for example to hand the next page to a client
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("a cursor always serializes");
+        json.iter().fold(String::new(), |mut token, byte| {
+            let _ = write!(token, "{:02x}", byte);
+            token
+        })
+    }
+
+    /// Reads a token made by `encode`. Fails with `TantivyError::InvalidArgument` if it isn't one
+    pub fn decode(token: &str) -> tantivy::Result<Self> {
+        let invalid = || TantivyError::InvalidArgument(format!("Invalid cursor: {}", token));
+        let json = (0..token.len())
+            .step_by(2)
+            .map(|i| {
+                token
+                    .get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        serde_json::from_slice(&json).map_err(|_| invalid())
+    }
+}
+
+/// The searchers of the generations cursors refer to, most recently used last
+#[derive(Clone, Default)]
+pub(crate) struct PinnedSearchers(Arc<Mutex<VecDeque<Searcher>>>);
+
+impl PinnedSearchers {
+    /// Keeps the searcher around for the cursors of its pages, dropping the least recently used one if there are too many
+    pub fn pin(&self, searcher: &Searcher) {
+        let mut searchers = self.0.lock().unwrap();
+        let generation = searcher.generation().generation_id();
+        searchers.retain(|pinned| pinned.generation().generation_id() != generation);
+        searchers.push_back(searcher.clone());
+        if searchers.len() > PINNED_SEARCHERS {
+            searchers.pop_front();
+        }
+    }
+
+    /// The searcher the cursor refers to
+    pub fn get(&self, cursor: &Cursor) -> tantivy::Result<Searcher> {
+        let searchers = self.0.lock().unwrap();
+        searchers
+            .iter()
+            .find(|searcher| searcher.generation().generation_id() == cursor.generation)
+            .cloned()
+            .ok_or_else(|| {
+                TantivyError::InvalidArgument(format!(
+                    "The cursor expired: generation {} of the index is no longer kept around, so the search has to start over",
+                    cursor.generation
+                ))
+            })
+    }
+}
+
+/// One page of results, along with the cursor to the next one
+#[derive(Debug)]
+pub struct Page<M> {
+    pub models: Vec<M>,
+    /// `None` once a page comes back with fewer models than were asked for
+    pub next: Option<Cursor>,
+}
+
+impl<M> From<SearchResults<M>> for Page<M> {
+    fn from(mut results: SearchResults<M>) -> Self {
+        let next = results.next.take();
+        Self {
+            models: results.into_models(),
+            next,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use tantivy::{
+    query::{EnableScoring, Explanation, Query, Scorer, Weight},
+    DocId, DocSet, Score, SegmentReader, TantivyError, TERMINATED,
+};
+
+/// Matches documents that match at least `minimum` of the clauses, scored by the sum of the clauses that matched.
+///
+/// tantivy's `BooleanQuery` can only require one of its should clauses, so this fills that gap.
+#[derive(Debug)]
+pub(crate) struct MinimumShouldMatchQuery {
+    clauses: Vec<Box<dyn Query>>,
+    minimum: usize,
+}
+
+impl MinimumShouldMatchQuery {
+    pub fn new(clauses: Vec<Box<dyn Query>>, minimum: usize) -> Self {
+        Self { clauses, minimum }
+    }
+}
+
+impl Clone for MinimumShouldMatchQuery {
+    fn clone(&self) -> Self {
+        Self {
+            clauses: self
+                .clauses
+                .iter()
+                .map(|clause| clause.box_clone())
+                .collect(),
+            minimum: self.minimum,
+        }
+    }
+}
+
+impl Query for MinimumShouldMatchQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        let weights = self
+            .clauses
+            .iter()
+            .map(|clause| clause.weight(enable_scoring))
+            .collect::<tantivy::Result<_>>()?;
+        Ok(Box::new(MinimumShouldMatchWeight {
+            weights,
+            minimum: self.minimum,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a tantivy::Term, bool)) {
+        for clause in &self.clauses {
+            clause.query_terms(visitor);
+        }
+    }
+}
+
+struct MinimumShouldMatchWeight {
+    weights: Vec<Box<dyn Weight>>,
+    minimum: usize,
+}
+
+impl Weight for MinimumShouldMatchWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let scorers = self
+            .weights
+            .iter()
+            .map(|weight| weight.scorer(reader, boost))
+            .collect::<tantivy::Result<_>>()?;
+        Ok(Box::new(MinimumShouldMatchScorer::new(
+            scorers,
+            self.minimum,
+        )))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({}) does not match",
+                doc
+            )));
+        }
+        let mut explanation = Explanation::new_with_string(
+            format!("MinimumShouldMatch({})", self.minimum),
+            scorer.score(),
+        );
+        for weight in &self.weights {
+            // Clauses that don't match this document simply have nothing to explain
+            if let Ok(child) = weight.explain(reader, doc) {
+                explanation.add_detail(child);
+            }
+        }
+        Ok(explanation)
+    }
+}
+
+struct MinimumShouldMatchScorer {
+    scorers: Vec<Box<dyn Scorer>>,
+    minimum: usize,
+    doc: DocId,
+}
+
+impl MinimumShouldMatchScorer {
+    fn new(scorers: Vec<Box<dyn Scorer>>, minimum: usize) -> Self {
+        let mut scorer = Self {
+            scorers,
+            minimum,
+            doc: TERMINATED,
+        };
+        if scorer.minimum <= scorer.scorers.len() {
+            scorer.find_match();
+        }
+        scorer
+    }
+
+    /// Moves to the first document, starting from where the clauses currently are, that enough clauses agree on
+    fn find_match(&mut self) -> DocId {
+        loop {
+            let candidate = self
+                .scorers
+                .iter()
+                .map(|scorer| scorer.doc())
+                .min()
+                .unwrap_or(TERMINATED);
+            if candidate == TERMINATED {
+                self.doc = TERMINATED;
+                return TERMINATED;
+            }
+            let matching = self
+                .scorers
+                .iter()
+                .filter(|scorer| scorer.doc() == candidate)
+                .count();
+            if matching >= self.minimum {
+                self.doc = candidate;
+                return candidate;
+            }
+            for scorer in &mut self.scorers {
+                if scorer.doc() == candidate {
+                    scorer.advance();
+                }
+            }
+        }
+    }
+}
+
+impl DocSet for MinimumShouldMatchScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        for scorer in &mut self.scorers {
+            if scorer.doc() == self.doc {
+                scorer.advance();
+            }
+        }
+        self.find_match()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Scorer for MinimumShouldMatchScorer {
+    fn score(&mut self) -> Score {
+        let doc = self.doc;
+        self.scorers
+            .iter_mut()
+            .filter(|scorer| scorer.doc() == doc)
+            .map(|scorer| scorer.score())
+            .sum()
+    }
+}
@@ -0,0 +1,167 @@
+use tantivy::{
+    query::{QueryParser, QueryParserError},
+    schema::Field,
+    Index, TantivyError,
+};
+
+use crate::index::ext::{ext_field::ExtField, ext_query::ExtQuery, ext_type_trait::ExtType};
+
+/// How `SearchIndex::typed_query_parser` should read queries typed by end users.
+///
+/// Words are required to all match unless `conjunction_by_default(false)` is set, so adding words narrows the results.
+///
+/// Example:
+/// ```ignore
+/// let config = QueryParserConfig::new()
+///     .field(MyModel::path_field())
+///     .boosted_field(MyModel::name_field(), 2.0)
+///     .fuzzy(MyModel::path_field(), 1);
+/// let (query, ignored) = index.typed_query_parser(&config)?.parse_lenient(user_input);
+/// ```
+#[derive(Clone, Debug)]
+pub struct QueryParserConfig {
+    default_fields: Vec<Field>,
+    boosts: Vec<(Field, f32)>,
+    fuzzy: Vec<(Field, Fuzziness)>,
+    conjunction_by_default: bool,
+}
+
+/// The largest edit distance tantivy builds fuzzy automatons for
+const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// How far off a word can be from the ones in a fuzzy field and still match
+#[derive(Clone, Copy, Debug)]
+pub struct Fuzziness {
+    /// How many edits a word can be away, at most 2. Larger distances make `typed_query_parser` fail
+    pub distance: u8,
+    /// Also match words that start with something within `distance` edits of the word
+    pub prefix: bool,
+    /// Count swapping two neighbouring letters as one edit rather than two
+    pub transpose_cost_one: bool,
+}
+
+impl Fuzziness {
+    pub fn new(distance: u8) -> Self {
+        Self {
+            distance,
+            prefix: false,
+            transpose_cost_one: true,
+        }
+    }
+}
+
+impl Default for QueryParserConfig {
+    fn default() -> Self {
+        Self {
+            default_fields: Vec::new(),
+            boosts: Vec::new(),
+            fuzzy: Vec::new(),
+            conjunction_by_default: true,
+        }
+    }
+}
+
+impl QueryParserConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search this field for words that don't name a field, as in `report` rather than `name:report`
+    pub fn field<T>(mut self, field: ExtField<T>) -> Self
+    where
+        T: ExtType,
+    {
+        self.default_fields.push(field.field());
+        self
+    }
+
+    /// Like `field`, but matches in this field have their score multiplied by `boost`
+    pub fn boosted_field<T>(mut self, field: ExtField<T>, boost: f32) -> Self
+    where
+        T: ExtType,
+    {
+        let field = field.field();
+        self.default_fields.push(field);
+        self.boosts.push((field, boost));
+        self
+    }
+
+    /// Let words in this field match within `distance` edits, so typos still find something
+    pub fn fuzzy<T>(self, field: ExtField<T>, distance: u8) -> Self
+    where
+        T: ExtType,
+    {
+        self.fuzziness(field, Fuzziness::new(distance))
+    }
+
+    /// Like `fuzzy`, with full control over how words are matched
+    pub fn fuzziness<T>(mut self, field: ExtField<T>, fuzziness: Fuzziness) -> Self
+    where
+        T: ExtType,
+    {
+        self.fuzzy.push((field.field(), fuzziness));
+        self
+    }
+
+    /// Whether every word has to match (the default), or just one of them
+    pub fn conjunction_by_default(mut self, conjunction: bool) -> Self {
+        self.conjunction_by_default = conjunction;
+        self
+    }
+
+    /// Fails with `TantivyError::InvalidArgument` if a fuzzy field allows more edits than tantivy supports
+    pub(crate) fn build(&self, index: &Index) -> tantivy::Result<ExtQueryParser> {
+        if let Some((field, fuzziness)) = self
+            .fuzzy
+            .iter()
+            .find(|(_, fuzziness)| fuzziness.distance > MAX_FUZZY_DISTANCE)
+        {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The fuzzy distance of the field `{}` is {}, but it can be at most {}",
+                index.schema().get_field_name(*field),
+                fuzziness.distance,
+                MAX_FUZZY_DISTANCE
+            )));
+        }
+        let mut parser = QueryParser::for_index(index, self.default_fields.clone());
+        for (field, boost) in &self.boosts {
+            parser.set_field_boost(*field, *boost);
+        }
+        for (field, fuzziness) in &self.fuzzy {
+            parser.set_field_fuzzy(
+                *field,
+                fuzziness.prefix,
+                fuzziness.distance,
+                fuzziness.transpose_cost_one,
+            );
+        }
+        if self.conjunction_by_default {
+            parser.set_conjunction_by_default();
+        }
+        Ok(ExtQueryParser(parser))
+    }
+}
+
+/// Turns text typed by end users into queries, as set up by a `QueryParserConfig`
+#[derive(Clone)]
+pub struct ExtQueryParser(QueryParser);
+
+impl ExtQueryParser {
+    /// Fails on the first mistake in the query, such as an unknown field or an unbalanced parenthesis
+    pub fn parse(&self, query: &str) -> tantivy::Result<ExtQuery> {
+        Ok(ExtQuery::from(self.0.parse_query(query)?))
+    }
+
+    /// Never fails: whatever can't be understood is left out of the query and returned alongside it.
+    ///
+    /// Meant for raw input from end users, where the ignored errors can be shown as hints rather than failing the search
+    pub fn parse_lenient(&self, query: &str) -> (ExtQuery, Vec<QueryParserError>) {
+        let (query, errors) = self.0.parse_query_lenient(query);
+        (ExtQuery::from(query), errors)
+    }
+
+    /// The underlying tantivy parser
+    pub fn inner(&self) -> &QueryParser {
+        &self.0
+    }
+}
@@ -0,0 +1,60 @@
+use std::{ops::Range, time::Duration};
+
+use tantivy::{DocAddress, Score};
+
+use super::{cursor::Cursor, sort::SortValue};
+
+/// A single model that matched the query, along with where it was found and how it was ranked
+#[derive(Debug, Clone)]
+pub struct Hit<M> {
+    pub model: M,
+    /// The score of the model, after every tweak and scoring function
+    pub score: Score,
+    /// Where the document of the model lives in the searcher the results came from
+    pub address: DocAddress,
+    /// The values the model was sorted by, one for every `order_by` field. Empty when results are ordered by score
+    pub sort_values: Vec<SortValue>,
+    /// One highlight for every `QueryBuilder::highlight` field, in the order they were asked for
+    pub highlights: Vec<Highlight>,
+}
+
+impl<M> Hit<M> {
+    /// The highlight of the field with the provided name, if it was asked for
+    pub fn highlight(&self, field_name: &str) -> Option<&Highlight> {
+        self.highlights
+            .iter()
+            .find(|highlight| highlight.field == field_name)
+    }
+}
+
+/// The part of a `Tokenized` field that best matches the query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    /// The name of the field
+    pub field: String,
+    /// At most as many characters of the field as were asked for. Empty if none of the words of the query are in the field
+    pub fragment: String,
+    /// The byte ranges of `fragment` that matched the query
+    pub ranges: Vec<Range<usize>>,
+    /// The fragment with HTML escaped, and the ranges that matched wrapped in `<b>` tags
+    pub html: String,
+}
+
+/// What `QueryBuilder::execute_results` found
+#[derive(Debug)]
+pub struct SearchResults<M> {
+    /// How many models match the query in total, not just the ones on this page
+    pub total_hits: usize,
+    pub hits: Vec<Hit<M>>,
+    /// How long the search took, including loading the documents of the hits
+    pub elapsed: Duration,
+    /// The cursor to the next page, or `None` if this page came back with fewer hits than were asked for
+    pub next: Option<Cursor>,
+}
+
+impl<M> SearchResults<M> {
+    /// Drops everything but the models
+    pub fn into_models(self) -> Vec<M> {
+        self.hits.into_iter().map(|hit| hit.model).collect()
+    }
+}
@@ -0,0 +1,108 @@
+use std::{fmt::Debug, sync::Arc};
+
+use tantivy::{
+    columnar::{Column, StrColumn},
+    fastfield::FastFieldReaders,
+    schema::{Field, Schema},
+    DocId, Score, SegmentReader, TantivyError,
+};
+
+use crate::index::ext::{ext_field::ExtField, ext_type_trait::ExtFastType};
+
+/// Sets up the score tweak of a segment, opening the columns it reads once rather than for every document.
+/// Tweaks run in the order they were added, each one getting the score of the last
+pub(crate) type ScoreTweak =
+    Arc<dyn Fn(&FastFieldContext) -> tantivy::Result<SegmentTweak> + Send + Sync>;
+
+/// Rewrites the score of a single document of the segment its `ScoreTweak` was set up for
+pub(crate) type SegmentTweak = Box<dyn Fn(DocId, Score) -> Score>;
+
+/// Typed access to the fast fields of the segment that is about to be scored
+pub struct FastFieldContext {
+    readers: FastFieldReaders,
+    schema: Schema,
+}
+
+impl FastFieldContext {
+    pub(crate) fn new(segment: &SegmentReader) -> Self {
+        Self {
+            readers: segment.fast_fields().clone(),
+            schema: segment.schema().clone(),
+        }
+    }
+
+    /// The column of the provided fast field in this segment.
+    ///
+    /// Fails with `TantivyError::SchemaError` if the field isn't a fast field in the index
+    ///
+    /// Example:
+    /// ```ignore
+    /// let popularity = ctx.fast(MyModel::popularity_field())?;
+    /// let first = popularity.first(doc).unwrap_or(0.0);
+    /// ```
+    pub fn fast<T>(&self, field: ExtField<T>) -> tantivy::Result<FastColumn<T::Target>>
+    where
+        T: ExtFastType,
+    {
+        self.column::<T>(field.field(), &field.name())
+    }
+
+    pub(crate) fn column<T>(
+        &self,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<FastColumn<T::Target>>
+    where
+        T: ExtFastType,
+    {
+        self.check_fast(field, field_name)?;
+        Ok(FastColumn {
+            column: T::column(&self.readers, field_name)?,
+        })
+    }
+
+    /// The column of a `FastStr` field, which holds term ordinals rather than the strings themselves.
+    /// `None` if no document of the segment has a value for it
+    pub(crate) fn str_column(
+        &self,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<Option<StrColumn>> {
+        self.check_fast(field, field_name)?;
+        self.readers.str(field_name)
+    }
+
+    fn check_fast(&self, field: Field, field_name: &str) -> tantivy::Result<()> {
+        if self.schema.get_field_entry(field).is_fast() {
+            Ok(())
+        } else {
+            Err(TantivyError::SchemaError(format!(
+                "The field `{}` is not a fast field, so its values can't be read while searching",
+                field_name
+            )))
+        }
+    }
+}
+
+/// The values of a fast field in a single segment
+pub struct FastColumn<T> {
+    column: Option<Column<T>>,
+}
+
+impl<T> FastColumn<T>
+where
+    T: PartialOrd + Copy + Debug + Send + Sync + 'static,
+{
+    /// The first value of the document, or `None` if it doesn't have one
+    pub fn first(&self, doc: DocId) -> Option<T> {
+        self.column.as_ref()?.first(doc)
+    }
+
+    /// Every value of the document
+    pub fn values(&self, doc: DocId) -> Vec<T> {
+        match &self.column {
+            Some(column) => column.values_for_doc(doc).collect(),
+            None => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use tantivy::DateTime;
+
+/// The curve a `Decay` follows as a date moves away from its origin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecayCurve {
+    /// Falls off slowly near the origin, then quickly, then slowly again
+    Gauss,
+    /// Falls off quickly near the origin, then ever more slowly
+    Exponential,
+    /// Falls off at a steady rate until it reaches zero at twice the scale
+    Linear,
+}
+
+/// Scales scores down the further a date is from `origin`, for example to favour recent models.
+///
+/// Dates within `offset` of the origin keep their full score. A date that is `scale` past the offset
+/// gets its score multiplied by `decay`, which is 0.5 unless set otherwise.
+///
+/// Example:
+/// ```ignore
+/// let week = Duration::from_secs(7 * 24 * 60 * 60);
+/// let decay = Decay::gauss(DateTime::from_utc(OffsetDateTime::now_utc()), week).offset(week);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Decay {
+    curve: DecayCurve,
+    origin: DateTime,
+    scale: Duration,
+    offset: Duration,
+    decay: f64,
+}
+
+impl Decay {
+    /// Panics if `scale` is zero
+    pub fn new(curve: DecayCurve, origin: DateTime, scale: Duration) -> Self {
+        assert!(!scale.is_zero(), "the scale of a decay can't be zero");
+        Self {
+            curve,
+            origin,
+            scale,
+            offset: Duration::ZERO,
+            decay: 0.5,
+        }
+    }
+
+    pub fn gauss(origin: DateTime, scale: Duration) -> Self {
+        Self::new(DecayCurve::Gauss, origin, scale)
+    }
+
+    pub fn exponential(origin: DateTime, scale: Duration) -> Self {
+        Self::new(DecayCurve::Exponential, origin, scale)
+    }
+
+    pub fn linear(origin: DateTime, scale: Duration) -> Self {
+        Self::new(DecayCurve::Linear, origin, scale)
+    }
+
+    /// How far from the origin a date can be before its score starts to decay
+    pub fn offset(mut self, offset: Duration) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// What a date that is `scale` past the offset gets its score multiplied by.
+    ///
+    /// Panics if `decay` isn't strictly between 0 and 1
+    pub fn decay(mut self, decay: f64) -> Self {
+        assert!(
+            decay > 0.0 && decay < 1.0,
+            "decay must be between 0 and 1, got {}",
+            decay
+        );
+        self.decay = decay;
+        self
+    }
+
+    /// What the score of a model with this date gets multiplied by, from 1 at the origin down towards 0
+    pub fn factor(&self, date: DateTime) -> f64 {
+        let micros = date
+            .into_timestamp_micros()
+            .abs_diff(self.origin.into_timestamp_micros());
+        let distance = (micros as f64 / 1_000_000.0 - self.offset.as_secs_f64()).max(0.0);
+        let scale = self.scale.as_secs_f64();
+        match self.curve {
+            DecayCurve::Gauss => {
+                let variance = -scale.powi(2) / (2.0 * self.decay.ln());
+                (-distance.powi(2) / (2.0 * variance)).exp()
+            }
+            DecayCurve::Exponential => (self.decay.ln() / scale * distance).exp(),
+            DecayCurve::Linear => {
+                let zero_at = scale / (1.0 - self.decay);
+                ((zero_at - distance) / zero_at).max(0.0)
+            }
+        }
+    }
+}
+
+/// What a `FieldValueFactor` does to the value of the field, after multiplying it by the factor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Modifier {
+    #[default]
+    None,
+    /// `log10(value)`
+    Log,
+    /// `log10(1 + value)`
+    Log1p,
+    /// `log10(2 + value)`
+    Log2p,
+    /// `ln(value)`
+    Ln,
+    /// `ln(1 + value)`
+    Ln1p,
+    /// `ln(2 + value)`
+    Ln2p,
+    Sqrt,
+    Square,
+    /// `1 / value`
+    Reciprocal,
+}
+
+/// Multiplies scores by the value of a numeric fast field, for example a popularity count.
+///
+/// The score gets multiplied by `modifier(factor * value)`. Anything that would make the score
+/// negative or not a number, such as the log of zero, multiplies it by zero instead.
+///
+/// Example:
+/// ```ignore
+/// let factor = FieldValueFactor::new().factor(1.2).modifier(Modifier::Sqrt).missing(1.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FieldValueFactor {
+    factor: f64,
+    modifier: Modifier,
+    missing: Option<f64>,
+}
+
+impl Default for FieldValueFactor {
+    fn default() -> Self {
+        Self {
+            factor: 1.0,
+            modifier: Modifier::None,
+            missing: None,
+        }
+    }
+}
+
+impl FieldValueFactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What the value gets multiplied by before the modifier is applied
+    pub fn factor(mut self, factor: f64) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    pub fn modifier(mut self, modifier: Modifier) -> Self {
+        self.modifier = modifier;
+        self
+    }
+
+    /// The value to use for models that don't have one. Without it, their score is left as it is
+    pub fn missing(mut self, value: f64) -> Self {
+        self.missing = Some(value);
+        self
+    }
+
+    /// What the score of a model with this value gets multiplied by
+    pub fn value_factor(&self, value: Option<f64>) -> f64 {
+        let Some(value) = value.or(self.missing) else {
+            return 1.0;
+        };
+        let value = value * self.factor;
+        let factor = match self.modifier {
+            Modifier::None => value,
+            Modifier::Log => value.log10(),
+            Modifier::Log1p => (1.0 + value).log10(),
+            Modifier::Log2p => (2.0 + value).log10(),
+            Modifier::Ln => value.ln(),
+            Modifier::Ln1p => value.ln_1p(),
+            Modifier::Ln2p => (2.0 + value).ln(),
+            Modifier::Sqrt => value.sqrt(),
+            Modifier::Square => value.powi(2),
+            Modifier::Reciprocal => value.recip(),
+        };
+        if factor.is_finite() && factor > 0.0 {
+            factor
+        } else {
+            0.0
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::cmp::Ordering;
+
+use tantivy::{
+    collector::{Collector, SegmentCollector},
+    DocAddress, DocId, Score, SegmentOrdinal, SegmentReader,
+};
+
+/// The order `TopDocs` ranks hits in: the greatest key first, then the lowest address
+pub(crate) fn rank<K>(a: &(K, DocAddress), b: &(K, DocAddress)) -> Ordering
+where
+    K: PartialOrd,
+{
+    b.0.partial_cmp(&a.0)
+        .unwrap_or(Ordering::Equal)
+        .then(a.1.cmp(&b.1))
+}
+
+/// Ranks the documents of a single segment
+pub(crate) trait SegmentRanker: 'static {
+    type Key;
+
+    fn key(&mut self, doc: DocId, score: Score) -> Self::Key;
+
+    /// Turns a key from outside the segment, such as the one a cursor holds, into one that compares with `key`
+    fn local(&self, key: &Self::Key) -> tantivy::Result<Self::Key>;
+
+    /// Turns a key of the segment into one that compares with the keys of every other segment
+    fn global(&self, key: Self::Key) -> Self::Key;
+}
+
+/// Like `TopDocs` with a score tweaker, but setting up the ranker of a segment can fail,
+/// and with `after` set only the hits that rank after it are collected.
+///
+/// Hits are ranked by their key and then by their address, so two searches on the same searcher
+/// always agree on which hits come after a given one.
+pub(crate) struct SearchAfter<K, F> {
+    after: Option<(K, DocAddress)>,
+    limit: usize,
+    offset: usize,
+    ranker_for_segment: F,
+}
+
+impl<K, F> SearchAfter<K, F> {
+    pub fn new(
+        after: Option<(K, DocAddress)>,
+        limit: usize,
+        offset: usize,
+        ranker_for_segment: F,
+    ) -> Self {
+        Self {
+            after,
+            limit,
+            offset,
+            ranker_for_segment,
+        }
+    }
+}
+
+impl<K, F, R> Collector for SearchAfter<K, F>
+where
+    K: PartialOrd + Clone + Send + Sync + 'static,
+    F: Fn(&SegmentReader) -> tantivy::Result<R> + Send + Sync,
+    R: SegmentRanker<Key = K>,
+{
+    type Fruit = Vec<(K, DocAddress)>;
+    type Child = SearchAfterSegment<K, R>;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let ranker = (self.ranker_for_segment)(reader)?;
+        let after = match &self.after {
+            Some((key, address)) => Some((ranker.local(key)?, *address)),
+            None => None,
+        };
+        Ok(SearchAfterSegment {
+            after,
+            keep: self.limit + self.offset,
+            segment_ord,
+            ranker,
+            hits: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(&self, fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut hits: Vec<_> = fruits.into_iter().flatten().collect();
+        hits.sort_by(rank);
+        Ok(hits
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect())
+    }
+}
+
+pub(crate) struct SearchAfterSegment<K, R> {
+    after: Option<(K, DocAddress)>,
+    keep: usize,
+    segment_ord: SegmentOrdinal,
+    ranker: R,
+    hits: Vec<(K, DocAddress)>,
+}
+
+impl<K, R> SearchAfterSegment<K, R>
+where
+    K: PartialOrd,
+{
+    /// Drops everything past the hits that can still make it into the results
+    fn truncate(&mut self) {
+        self.hits.sort_by(rank);
+        self.hits.truncate(self.keep);
+    }
+}
+
+impl<K, R> SegmentCollector for SearchAfterSegment<K, R>
+where
+    K: PartialOrd + Clone + Send + Sync + 'static,
+    R: SegmentRanker<Key = K>,
+{
+    type Fruit = Vec<(K, DocAddress)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let hit = (
+            self.ranker.key(doc, score),
+            DocAddress::new(self.segment_ord, doc),
+        );
+        if let Some(after) = &self.after {
+            if rank(&hit, after) != Ordering::Greater {
+                return;
+            }
+        }
+        self.hits.push(hit);
+        // Sorting every so often keeps memory bounded without sorting on every hit
+        if self.hits.len() >= 2 * self.keep.max(1) {
+            self.truncate();
+        }
+    }
+
+    fn harvest(mut self) -> Self::Fruit {
+        self.truncate();
+        let ranker = self.ranker;
+        self.hits
+            .into_iter()
+            .map(|(key, address)| (ranker.global(key), address))
+            .collect()
+    }
+}
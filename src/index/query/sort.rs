@@ -0,0 +1,310 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tantivy::{columnar::StrColumn, schema::Field, DateTime, DocId, Order, Score};
+
+use crate::index::ext::{
+    ext_field::ExtField,
+    ext_type::{ExtDate, ExtF64, ExtFastText, ExtI64, ExtU64},
+    ext_type_trait::ExtType,
+};
+
+use super::score::{FastColumn, FastFieldContext};
+
+/// The value of a single sort field of a model, as read from its fast field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SortValue {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Str(String),
+    Date(DateTime),
+    /// The model has no value for the field, so it was sorted after every model that does
+    Missing,
+}
+
+impl SortValue {
+    /// Compares two values of the same field. Anything compares greater than a missing value
+    fn compare(&self, other: &SortValue) -> Ordering {
+        match (self, other) {
+            (SortValue::U64(a), SortValue::U64(b)) => a.cmp(b),
+            (SortValue::I64(a), SortValue::I64(b)) => a.cmp(b),
+            (SortValue::F64(a), SortValue::F64(b)) => a.total_cmp(b),
+            (SortValue::Str(a), SortValue::Str(b)) => a.cmp(b),
+            (SortValue::Date(a), SortValue::Date(b)) => a.cmp(b),
+            (SortValue::Missing, SortValue::Missing) => Ordering::Equal,
+            (SortValue::Missing, _) => Ordering::Less,
+            (_, SortValue::Missing) => Ordering::Greater,
+            // A field always has the same type, so this only happens if the schema changed under the index
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// An `ExtType` that search results can be ordered by
+pub trait ExtSortType: ExtType {
+    /// Opens the column of the field in a segment, to read the first value of each document
+    fn sort_column(
+        ctx: &FastFieldContext,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<SortColumn>;
+}
+
+impl ExtSortType for ExtU64 {
+    fn sort_column(
+        ctx: &FastFieldContext,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<SortColumn> {
+        Ok(SortColumn(Column::U64(
+            ctx.column::<Self>(field, field_name)?,
+        )))
+    }
+}
+
+impl ExtSortType for ExtI64 {
+    fn sort_column(
+        ctx: &FastFieldContext,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<SortColumn> {
+        Ok(SortColumn(Column::I64(
+            ctx.column::<Self>(field, field_name)?,
+        )))
+    }
+}
+
+impl ExtSortType for ExtF64 {
+    fn sort_column(
+        ctx: &FastFieldContext,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<SortColumn> {
+        Ok(SortColumn(Column::F64(
+            ctx.column::<Self>(field, field_name)?,
+        )))
+    }
+}
+
+impl ExtSortType for ExtDate {
+    fn sort_column(
+        ctx: &FastFieldContext,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<SortColumn> {
+        Ok(SortColumn(Column::Date(
+            ctx.column::<Self>(field, field_name)?,
+        )))
+    }
+}
+
+/// Only `FastStr` fields have a column of term ordinals to sort by
+impl ExtSortType for ExtFastText {
+    fn sort_column(
+        ctx: &FastFieldContext,
+        field: Field,
+        field_name: &str,
+    ) -> tantivy::Result<SortColumn> {
+        Ok(SortColumn(Column::Str(ctx.str_column(field, field_name)?)))
+    }
+}
+
+/// The column of a sort field in a single segment
+pub struct SortColumn(Column);
+
+enum Column {
+    U64(FastColumn<u64>),
+    I64(FastColumn<i64>),
+    F64(FastColumn<f64>),
+    Date(FastColumn<DateTime>),
+    /// `None` if no document of the segment has a value for the field
+    Str(Option<StrColumn>),
+}
+
+impl SortColumn {
+    /// The first value of the document. Strings come as their term ordinal, see `KeyValue::Ord`
+    pub(crate) fn value(&self, doc: DocId) -> KeyValue {
+        let value = match &self.0 {
+            Column::U64(column) => column.first(doc).map(SortValue::U64),
+            Column::I64(column) => column.first(doc).map(SortValue::I64),
+            Column::F64(column) => column.first(doc).map(SortValue::F64),
+            Column::Date(column) => column.first(doc).map(SortValue::Date),
+            Column::Str(column) => {
+                let ord = column
+                    .as_ref()
+                    .and_then(|column| column.term_ords(doc).next());
+                return match ord {
+                    Some(ord) => KeyValue::Ord(2 * ord + 1),
+                    None => KeyValue::Value(SortValue::Missing),
+                };
+            }
+        };
+        KeyValue::Value(value.unwrap_or(SortValue::Missing))
+    }
+
+    /// Turns a value from outside the segment, such as one a cursor holds, into one that compares with `value`
+    pub(crate) fn local(&self, value: &KeyValue) -> tantivy::Result<KeyValue> {
+        let (Column::Str(Some(column)), KeyValue::Value(SortValue::Str(value))) = (&self.0, value)
+        else {
+            return Ok(value.clone());
+        };
+        // Every term `value` is greater than comes before it, so it falls in between the ordinals
+        // of the terms around it, or right on the ordinal of the term that is equal to it
+        let dictionary = column.dictionary();
+        let mut term = Vec::new();
+        let (mut low, mut high) = (0, dictionary.num_terms() as u64);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            dictionary.ord_to_term(middle, &mut term)?;
+            if term.as_slice() < value.as_bytes() {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        let equal = low < dictionary.num_terms() as u64
+            && dictionary.ord_to_term(low, &mut term)?
+            && term == value.as_bytes();
+        Ok(KeyValue::Ord(if equal { 2 * low + 1 } else { 2 * low }))
+    }
+
+    /// Turns a value of the segment into one that compares with the values of every other segment
+    pub(crate) fn global(&self, value: KeyValue) -> KeyValue {
+        let (Column::Str(Some(column)), KeyValue::Ord(ord)) = (&self.0, &value) else {
+            return value;
+        };
+        let mut string = String::new();
+        match column.ord_to_str(ord / 2, &mut string) {
+            Ok(true) => KeyValue::Value(SortValue::Str(string)),
+            _ => KeyValue::Value(SortValue::Missing),
+        }
+    }
+}
+
+/// A sort value as it gets compared while ranking
+#[derive(Clone, Debug)]
+pub(crate) enum KeyValue {
+    Value(SortValue),
+    /// A string while the segment it comes from is being collected. Comparing term ordinals is much cheaper
+    /// than comparing strings, so the strings are only looked up for the hits the segment keeps.
+    ///
+    /// The term with ordinal `n` is `2n + 1`. A string that isn't a term of the segment is `2n`,
+    /// where `n` is the number of terms that come before it
+    Ord(u64),
+}
+
+impl KeyValue {
+    /// Compares two values of the same field. Anything compares greater than a missing value
+    fn compare(&self, other: &KeyValue) -> Ordering {
+        match (self, other) {
+            (KeyValue::Value(a), KeyValue::Value(b)) => a.compare(b),
+            (KeyValue::Ord(a), KeyValue::Ord(b)) => a.cmp(b),
+            (KeyValue::Ord(_), KeyValue::Value(SortValue::Missing)) => Ordering::Greater,
+            (KeyValue::Value(SortValue::Missing), KeyValue::Ord(_)) => Ordering::Less,
+            // Values of a segment are turned into ordinals the same way, so this never happens
+            _ => Ordering::Equal,
+        }
+    }
+
+    fn is_missing(&self) -> bool {
+        matches!(self, KeyValue::Value(SortValue::Missing))
+    }
+
+    fn into_sort_value(self) -> SortValue {
+        match self {
+            KeyValue::Value(value) => value,
+            // Keys only leave the segment they were collected in once every ordinal was turned into its string
+            KeyValue::Ord(_) => SortValue::Missing,
+        }
+    }
+}
+
+/// One field of a `QueryBuilder::order_by` chain
+#[derive(Clone)]
+pub(crate) struct SortField {
+    field: Field,
+    name: String,
+    pub order: Order,
+    open: fn(&FastFieldContext, Field, &str) -> tantivy::Result<SortColumn>,
+}
+
+impl SortField {
+    pub fn new<T>(field: ExtField<T>, order: Order) -> Self
+    where
+        T: ExtSortType,
+    {
+        Self {
+            field: field.field(),
+            name: field.name(),
+            order,
+            open: T::sort_column,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Opens the field for a segment, once before any of its documents get sorted
+    pub fn open(&self, ctx: &FastFieldContext) -> tantivy::Result<SortColumn> {
+        (self.open)(ctx, self.field, &self.name)
+    }
+}
+
+/// Where a match ends up when results are ordered by fields. The greatest key comes first.
+///
+/// Fields are compared in the order they were added, the score breaks whatever ties are left,
+/// and missing values come last whichever way their field is ordered
+#[derive(Clone, Debug)]
+pub(crate) struct SortKey {
+    pub values: Vec<KeyValue>,
+    pub score: Score,
+    orders: Arc<[Order]>,
+}
+
+impl SortKey {
+    pub fn new(values: Vec<KeyValue>, score: Score, orders: Arc<[Order]>) -> Self {
+        Self {
+            values,
+            score,
+            orders,
+        }
+    }
+
+    /// The values the model was sorted by
+    pub fn sort_values(&self) -> Vec<SortValue> {
+        self.values
+            .iter()
+            .cloned()
+            .map(KeyValue::into_sort_value)
+            .collect()
+    }
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        for ((value, other_value), order) in self
+            .values
+            .iter()
+            .zip(&other.values)
+            .zip(self.orders.iter())
+        {
+            let ordering = if value.is_missing() || other_value.is_missing() || !order.is_asc() {
+                value.compare(other_value)
+            } else {
+                value.compare(other_value).reverse()
+            };
+            if ordering != Ordering::Equal {
+                return Some(ordering);
+            }
+        }
+        Some(self.score.total_cmp(&other.score))
+    }
+}
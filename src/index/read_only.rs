@@ -0,0 +1,130 @@
+use std::{cell::RefCell, marker::PhantomData, ops::Deref, path::PathBuf, sync::Arc};
+
+use tantivy::{schema::Schema, Index, ReloadPolicy};
+
+use crate::entity::entity_trait;
+
+use super::{index_builder::IndexLocation, searchable::Searchable};
+
+/// A reader-only handle to a search index that another process may be writing to.
+///
+/// It never takes the writer lock, so any number of these can be opened next to a `SearchIndex`.
+/// Because there are no mutating methods, the index can't be changed through this handle.
+/// Everything to do with searching lives on `Searchable`, which this dereferences to.
+///
+/// Because all of the underlying data wraps an `Arc`, this can be negligibly cloned
+pub struct ReadOnlySearchIndex<M>
+where
+    M: entity_trait::Index,
+{
+    searchable: Searchable<M>,
+}
+
+impl<M> Clone for ReadOnlySearchIndex<M>
+where
+    M: entity_trait::Index,
+{
+    fn clone(&self) -> Self {
+        Self {
+            searchable: self.searchable.clone(),
+        }
+    }
+}
+
+impl<M> ReadOnlySearchIndex<M>
+where
+    M: entity_trait::Index,
+{
+    /// Opens the existing index at the provided path
+    pub fn open(index_path: PathBuf, reload_policy: ReloadPolicy) -> tantivy::Result<Self> {
+        Self::open_in(IndexLocation::Path(index_path), reload_policy)
+    }
+
+    pub(crate) fn open_in(
+        location: IndexLocation,
+        reload_policy: ReloadPolicy,
+    ) -> tantivy::Result<Self> {
+        let index = match location {
+            IndexLocation::Path(index_path) => Index::open_in_dir(index_path)?,
+            IndexLocation::Directory(directory) => Index::open(directory)?,
+        };
+        Searchable::<M>::check_schema(&index)?;
+        let index = Arc::new(index);
+        let reader = index
+            .reader_builder()
+            .reload_policy(reload_policy)
+            .try_into()?;
+        Ok(Self {
+            searchable: Searchable::new(index, reader),
+        })
+    }
+
+    /// Pick up the latest commit made by the writing process.
+    ///
+    /// Only needed with `ReloadPolicy::Manual`, as `ReloadPolicy::OnCommitWithDelay` watches the index for commits.
+    pub fn reload(&self) -> tantivy::Result<()> {
+        self.searchable.reader().reload()
+    }
+
+    /// Get the schema that this index uses
+    pub fn schema() -> &'static Schema {
+        M::schema()
+    }
+}
+
+impl<M> Deref for ReadOnlySearchIndex<M>
+where
+    M: entity_trait::Index,
+{
+    type Target = Searchable<M>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.searchable
+    }
+}
+
+pub struct ReadOnlySearchIndexBuilder<M>
+where
+    M: entity_trait::Index,
+{
+    location: IndexLocation,
+    reload_policy: RefCell<ReloadPolicy>,
+
+    phantom: PhantomData<M>,
+}
+
+impl<M> ReadOnlySearchIndexBuilder<M>
+where
+    M: entity_trait::Index,
+{
+    pub fn new(save_path: PathBuf, reload_policy: ReloadPolicy) -> Self {
+        Self::in_location(IndexLocation::Path(save_path), reload_policy)
+    }
+
+    pub(crate) fn in_location(location: IndexLocation, reload_policy: ReloadPolicy) -> Self {
+        Self {
+            location,
+            reload_policy: RefCell::new(reload_policy),
+            phantom: PhantomData,
+        }
+    }
+
+    /// When the reader should pick up commits made by the writing process.
+    ///
+    /// `ReloadPolicy::OnCommitWithDelay` watches the index files, while `ReloadPolicy::Manual`
+    /// requires calling `ReadOnlySearchIndex::reload`
+    pub fn with_reload_policy(self, reload_policy: ReloadPolicy) -> Self {
+        *self.reload_policy.borrow_mut() = reload_policy;
+        self
+    }
+
+    /// Panics if the index can't be opened. See `try_build` for the fallible version
+    pub fn build(self) -> ReadOnlySearchIndex<M> {
+        self.try_build().unwrap()
+    }
+
+    /// Fails if there is no index at the provided path yet
+    pub fn try_build(self) -> tantivy::Result<ReadOnlySearchIndex<M>> {
+        ReadOnlySearchIndex::open_in(self.location, *self.reload_policy.borrow())
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+/// Decides when the `IndexWriter` should get recycled.
+///
+/// Policies are checked whenever the writer finishes processing entries, so time based policies
+/// only kick in on the next mutation after the duration has passed.
+#[derive(Clone, Debug)]
+pub enum RecyclePolicy {
+    /// Recycle once more than this many entries have been processed by the writer
+    EntriesProcessed(usize),
+    /// Recycle once the writer has been alive for longer than this
+    Elapsed(Duration),
+    /// Recycle once the documents indexed by the writer add up to more than this many bytes.
+    ///
+    /// Every text and bytes value counts with its length and every other value with 8 bytes. This is a rough
+    /// stand-in for how much the writer has buffered, not a measurement of how much memory it uses.
+    BytesIndexed(usize),
+    /// Recycle once the index has more than this many searchable segments.
+    ///
+    /// Recycling waits for pending merges, so this should be paired with a merge policy that can
+    /// actually bring the segment count back below the limit.
+    SegmentCount(usize),
+    /// Recycle as soon as any of the provided policies says so
+    Any(Vec<RecyclePolicy>),
+    /// Only recycle when asked to explicitly
+    Never,
+}
+
+impl Default for RecyclePolicy {
+    fn default() -> Self {
+        Self::EntriesProcessed(1_000_000)
+    }
+}
+
+/// Why an `IndexWriter` got recycled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecycleReason {
+    EntriesProcessed,
+    Elapsed,
+    BytesIndexed,
+    SegmentCount,
+    /// The writer was recycled explicitly, for example by `SearchIndex::recycle_writer`
+    Manual,
+}
+
+/// Sent to every subscriber whenever the `IndexWriter` gets recycled
+#[derive(Clone, Debug)]
+pub struct RecycleEvent {
+    pub reason: RecycleReason,
+    /// How many entries the old writer processed
+    pub entries_processed: usize,
+    /// The size of the documents indexed by the old writer, counted the way `RecyclePolicy::BytesIndexed` does
+    pub bytes_indexed: usize,
+    /// How long the old writer was alive for
+    pub writer_age: Duration,
+}
+
+/// What the recycler has observed about the current writer
+pub(crate) struct WriterStats {
+    pub entries_processed: usize,
+    pub bytes_indexed: usize,
+    pub writer_age: Duration,
+}
+
+impl RecyclePolicy {
+    /// Returns the reason to recycle, if any.
+    ///
+    /// `segment_count` is only called if the policy depends on it.
+    pub(crate) fn check<F>(&self, stats: &WriterStats, segment_count: &F) -> Option<RecycleReason>
+    where
+        F: Fn() -> usize,
+    {
+        match self {
+            Self::EntriesProcessed(max) => {
+                (stats.entries_processed > *max).then_some(RecycleReason::EntriesProcessed)
+            }
+            Self::Elapsed(max) => (stats.writer_age > *max).then_some(RecycleReason::Elapsed),
+            Self::BytesIndexed(max) => {
+                (stats.bytes_indexed > *max).then_some(RecycleReason::BytesIndexed)
+            }
+            Self::SegmentCount(max) => {
+                (segment_count() > *max).then_some(RecycleReason::SegmentCount)
+            }
+            Self::Any(policies) => policies
+                .iter()
+                .find_map(|policy| policy.check(stats, segment_count)),
+            Self::Never => None,
+        }
+    }
+}
@@ -1,29 +1,38 @@
-use std::{fs, marker::PhantomData, path::PathBuf, sync::Arc, time::Duration};
+use std::{fs, marker::PhantomData, ops::Deref, path::PathBuf, sync::Arc};
 
+use futures::Stream;
 use tantivy::{
-    query::{Query, QueryParser},
-    schema::{Field, Schema},
-    Index, IndexReader, IndexWriter, Term,
+    collector::Count, query::Query, schema::Schema, Index, IndexReader, TantivyError, Term,
 };
-use tokio::sync::RwLock;
+use tokio::sync::broadcast;
 
-use crate::{entity::entity_trait, util::async_retry};
+use crate::entity::entity_trait;
 
 use super::{
-    backend::TantivyBackend, query::builder::QueryBuilder, writer_recycler::IndexWriterRecycler,
+    backend::TantivyBackend,
+    index_builder::{IndexLocation, SearchIndexOptions},
+    ingest::{self, IngestPolicy, IngestSummary},
+    patch::Patch,
+    recycle_policy::{RecycleEvent, RecyclePolicy, RecycleReason},
+    searchable::Searchable,
+    transaction::Transaction,
+    writer_actor::WriterHandle,
+    writer_recycler::IndexWriterRecycler,
 };
 
 /// A tantivy search index over instances of the provided struct.
 ///
+/// Everything to do with searching lives on `Searchable`, which this dereferences to.
+///
 /// Because all of the underlying data wraps an `Arc`, this can be negligibly cloned
 #[derive(Clone)]
 pub struct SearchIndex<M>
 where
     M: entity_trait::Index,
 {
-    writer_recycler: IndexWriterRecycler,
-    reader: IndexReader,
-    index: Arc<tantivy::Index>,
+    pub(super) writer: WriterHandle,
+    searchable: Searchable<M>,
+    ingest_policy: IngestPolicy,
 
     phantom: PhantomData<M>,
 }
@@ -32,141 +41,297 @@ impl<M> SearchIndex<M>
 where
     M: entity_trait::Index,
 {
-    pub fn new(index_path: PathBuf, buffer_size:usize, entries_before_recycle:usize) -> Self {
+    pub fn new(index_path: PathBuf, buffer_size: usize, entries_before_recycle: usize) -> Self {
+        Self::with_options(
+            IndexLocation::Path(index_path),
+            SearchIndexOptions {
+                memory_budget_in_bytes: buffer_size,
+                recycle_policy: RecyclePolicy::EntriesProcessed(entries_before_recycle),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    /// Opens the index, blocking the current thread for about the lock timeout if another writer holds the directory lock
+    pub(crate) fn with_options(
+        location: IndexLocation,
+        options: SearchIndexOptions,
+    ) -> tantivy::Result<Self> {
+        let (index, reader) = Self::open_index(location, &options)?;
+        let writer_recycler = IndexWriterRecycler::new(
+            Arc::clone(&index),
+            options.writer_options(),
+            options.recycle_policy.clone(),
+        )?;
+        Self::from_parts(writer_recycler, reader, index, &options)
+    }
+
+    /// Like `with_options`, but waits for the directory lock without blocking the current thread
+    pub(crate) async fn with_options_waiting_for_lock(
+        location: IndexLocation,
+        options: SearchIndexOptions,
+    ) -> tantivy::Result<Self> {
+        let (index, reader) = Self::open_index(location, &options)?;
+        let writer_recycler = IndexWriterRecycler::new_waiting_for_lock(
+            Arc::clone(&index),
+            options.writer_options(),
+            options.recycle_policy.clone(),
+        )
+        .await?;
+        Self::from_parts(writer_recycler, reader, index, &options)
+    }
+
+    fn open_index(
+        location: IndexLocation,
+        options: &SearchIndexOptions,
+    ) -> tantivy::Result<(Arc<Index>, IndexReader)> {
         let schema = M::schema();
         // Create the Tantivy index
-        let index = if index_path.exists() {
+        let index = match location {
             // If the index directory exists, open the existing index
-            println!("Opening existing index at {:?}", index_path);
-            Index::open_in_dir(index_path)
-        } else {
+            IndexLocation::Path(index_path) if index_path.exists() => {
+                println!("Opening existing index at {:?}", index_path);
+                Index::open_in_dir(index_path)?
+            }
             // If the index directory doesn't exist, create a new index
-            println!("Creating a new index at {:?}", index_path);
-            fs::create_dir_all(index_path.clone()).expect("could not create output directory");
-            Index::create_in_dir(index_path, schema.clone())
+            IndexLocation::Path(index_path) => {
+                println!("Creating a new index at {:?}", index_path);
+                fs::create_dir_all(index_path.clone())?;
+                Index::builder()
+                    .schema(schema.clone())
+                    .settings(options.index_settings.clone())
+                    .create_in_dir(index_path)?
+            }
+            // The same goes for an index kept in a `Directory`
+            IndexLocation::Directory(directory) if Index::exists(directory.as_ref())? => {
+                Index::open(directory)?
+            }
+            IndexLocation::Directory(directory) => Index::builder()
+                .schema(schema.clone())
+                .settings(options.index_settings.clone())
+                .open_or_create(directory)?,
         };
-        let index = Arc::new(index.unwrap());
+        // An index created from an older version of the model can't be read as the current one
+        Searchable::<M>::check_schema(&index)?;
+        let index = Arc::new(index);
 
         let reader = index
             .reader_builder()
-            .reload_policy(tantivy::ReloadPolicy::OnCommitWithDelay)
-            .try_into()
-            .unwrap();
+            .reload_policy(options.reload_policy)
+            .try_into()?;
 
-        let writer_recycler =
-            IndexWriterRecycler::new(Arc::clone(&index), buffer_size, entries_before_recycle);
+        Ok((index, reader))
+    }
 
-        Self {
+    fn from_parts(
+        writer_recycler: IndexWriterRecycler,
+        reader: IndexReader,
+        index: Arc<Index>,
+        options: &SearchIndexOptions,
+    ) -> tantivy::Result<Self> {
+        let writer = WriterHandle::spawn(
             writer_recycler,
-            reader,
-            index,
+            reader.clone(),
+            options.reload_after_commit,
+            options.queue_capacity,
+        )?;
+        Ok(Self {
+            writer,
+            searchable: Searchable::new(index, reader),
+            ingest_policy: options.ingest_policy.clone(),
             phantom: PhantomData,
-        }
+        })
     }
 
     /// Adds the provided models to the search index and then commits the changes.
     pub async fn add(&self, models: &[M]) -> tantivy::Result<()> {
-        let writer = self.writer_recycler.get_writer();
-        let models_len = models.len();
-        {
-            let mut writer_lock = writer.write().await;
-            let writer_lock = writer_lock.as_mut().unwrap();
-            for model in models {
-                // Delete by the primary key
-                let primary_key_term = model.get_primary_key();
-                writer_lock.delete_term(primary_key_term);
-
-                writer_lock.add_document(model.as_document())?;
-            }
-            self.commit(writer_lock).await?;
-        }
-        // Writer lock must be dropped so this function can use it
-        self.writer_recycler
-            .register_entries_processed(models_len)
-            .await?;
-        Ok(())
+        self.transaction().add(models).commit().await
+    }
+
+    /// Adds every model the stream produces, committing them in batches according to the ingest policy.
+    ///
+    /// The stream only gets polled for the next batch while the previous one is being committed,
+    /// so a fast producer is held back to the pace of the writer. A batch that fails doesn't stop the
+    /// ingest; its models are counted as failed in the returned summary instead.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let summary = index.ingest(crawler.models()).await;
+    /// println!("{} added, {} replaced, {} failed", summary.added, summary.replaced, summary.failed);
+    /// ```
+    pub async fn ingest<S>(&self, models: S) -> IngestSummary
+    where
+        S: Stream<Item = M>,
+    {
+        ingest::ingest(models, &self.ingest_policy, |operations| {
+            self.writer
+                .run(move |actor| ingest::write_batch(actor, operations))
+        })
+        .await
+    }
+
+    /// Like `ingest`, but for models that are already at hand
+    pub async fn ingest_iter<I>(&self, models: I) -> IngestSummary
+    where
+        I: IntoIterator<Item = M>,
+    {
+        self.ingest(futures::stream::iter(models)).await
     }
 
     /// Removes the provided models from the search index and then commits the changes.
-    pub async fn remove<'a, T>(&self, models: &[M]) -> tantivy::Result<()> {
-        let models_len = models.len();
-        let writer = self.get_writer();
-        {
-            let mut writer_lock = writer.write().await;
-            let writer_lock = writer_lock.as_mut().unwrap();
-            for model in models {
-                let primary_key_term = model.get_primary_key();
-                writer_lock.delete_term(primary_key_term);
-            }
-            self.commit(writer_lock).await?;
-        }
-        // Writer lock must be dropped so this function can use it
-        self.writer_recycler
-            .register_entries_processed(models_len)
-            .await?;
-        Ok(())
+    pub async fn remove(&self, models: &[M]) -> tantivy::Result<()> {
+        self.transaction().remove(models).commit().await
     }
 
     /// Removes all models from the search index with the provided term
     ///
     /// Example:
-    /// ```rust
+    /// ```ignore
     /// let term = MyModel::name_field().term(String::from("Joe"));
     /// index.remove_by_terms(vec![term]).await;
     /// ```
     pub async fn remove_by_terms(&self, terms: Vec<Term>) -> tantivy::Result<()> {
-        let writer = self.get_writer();
-        let terms_len = terms.len();
-        {
-            let mut writer_lock = writer.write().await;
-            let writer_lock = writer_lock.as_mut().unwrap();
-            for term in terms {
-                writer_lock.delete_term(term);
-            }
-            self.commit(writer_lock).await?;
-        }
-        self.writer_recycler
-            .register_entries_processed(terms_len)
-            .await
+        self.transaction().remove_by_terms(terms).commit().await
     }
 
-    /// Attempt to commit all pending changes.
+    /// Removes all models from the search index that match the provided query and then commits the changes.
+    ///
+    /// Returns the number of models that were deleted.
     ///
-    /// This function will retry up to 3 times in case of errors.
-    async fn commit(&self, writer: &mut IndexWriter) -> tantivy::Result<()> {
-        async_retry::retry_with_backoff(|_| writer.commit(), 3, Duration::from_millis(100)).await?;
+    /// Example:
+    /// ```ignore
+    /// let query = RangeQuery::new_date(String::from("date"), DateTime::MIN..cutoff);
+    /// let deleted = index.remove_by_query(Box::new(query)).await?;
+    /// ```
+    pub async fn remove_by_query(&self, query: Box<dyn Query>) -> tantivy::Result<usize> {
+        self.writer
+            .run(move |actor| {
+                // Nothing else can commit while the writer task runs this,
+                // so the searcher sees exactly what is about to be deleted
+                let deleted = actor.latest_searcher()?.search(&query, &Count)?;
+                actor.writer()?.delete_query(query)?;
+                actor.commit()?;
+                actor.recycler.register_entries_processed(deleted, 0)?;
+                Ok(deleted)
+            })
+            .await
+    }
 
-        Ok(())
+    /// Loads the model with the provided primary key, passes it to `update` and then re-adds it and commits the changes.
+    ///
+    /// `update` runs without holding up the writer task. If another write changes the model before the re-add,
+    /// the model gets loaded again and `update` runs again on the fresh copy, so no change gets lost.
+    ///
+    /// Returns the updated model, or `None` if there is no model with that primary key.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let key = MyModel::name_field().term(String::from("Joe"));
+    /// index.update(key, |model| model.popularity = 11.0.into()).await?;
+    /// ```
+    pub async fn update<F>(&self, key: Term, mut update: F) -> tantivy::Result<Option<M>>
+    where
+        F: FnMut(&mut M),
+    {
+        self.writer
+            .replace_by_key(key, |score, doc| {
+                let mut model = M::from_document(doc, score);
+                update(&mut model);
+                // The update may have changed the primary key, so both the old and new one get cleared
+                let new_key = model.get_primary_key();
+                let doc = model.as_document();
+                (model, new_key, doc)
+            })
+            .await
     }
 
-    /// Get the query parser for this search index
-    pub fn query_parser(&self, default_fields: Vec<Field>) -> QueryParser {
-        QueryParser::for_index(&self.index, default_fields)
+    /// Start a typed patch of the stored model with the provided primary key.
+    ///
+    /// Unlike `update`, only the fields that are set on the patch get touched.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let key = MyModel::name_field().term(String::from("Joe"));
+    /// index
+    ///     .patch(key)
+    ///     .set(MyModel::popularity_field(), 11.0)
+    ///     .apply()
+    ///     .await?;
+    /// ```
+    pub fn patch(&self, key: Term) -> Patch<'_, M> {
+        Patch::new(self, key)
     }
 
-    pub fn query<'a, Q>(&self, query: &'a Q, max_results: usize) -> QueryBuilder<'a, Q, M>
-    where
-        Q: Query + Sized,
-    {
-        let searcher = self.reader.searcher();
-        QueryBuilder::new(query, searcher, max_results)
+    /// Removes every model from the search index, commits the changes and reloads the reader
+    /// so that subsequent queries see an empty index.
+    pub async fn clear(&self) -> tantivy::Result<()> {
+        self.writer
+            .run(|actor| {
+                actor.writer()?.delete_all_documents()?;
+                actor.commit()?;
+                // The writer has nothing left to account for
+                actor.recycler.reset_entries_processed();
+                actor.reload_reader()
+            })
+            .await
     }
 
-    pub fn scored_doc_to_model(&self, doc: (f64, tantivy::DocAddress)) -> tantivy::Result<M> {
-        let searcher = self.reader.searcher();
-        let (score, address) = doc;
+    /// Merges all searchable segments down to at most `max_segments` segments and then garbage-collects
+    /// the files that are no longer used.
+    ///
+    /// The writer gets recycled first so that no background merge is still running.
+    pub async fn optimize(&self, max_segments: usize) -> tantivy::Result<()> {
+        if max_segments == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "max_segments must be at least 1".to_string(),
+            ));
+        }
+        self.writer
+            .run(move |actor| {
+                actor.recycler.replace_writer(RecycleReason::Manual)?;
 
-        let doc = searcher.doc(address)?;
+                let segment_ids = actor.index().searchable_segment_ids()?;
+                let writer = actor.writer()?;
+                if segment_ids.len() > max_segments {
+                    let chunk_size = segment_ids.len().div_ceil(max_segments);
+                    for chunk in segment_ids.chunks(chunk_size) {
+                        if chunk.len() > 1 {
+                            writer.merge(chunk).wait()?;
+                        }
+                    }
+                }
+                writer.garbage_collect_files().wait()?;
 
-        Ok(M::from_document(doc, score as f32))
+                actor.refresh_reader()
+            })
+            .await
     }
 
-    pub async fn recycle_writer(&self) -> tantivy::Result<()>{
-        self.writer_recycler.replace_writer().await
+    /// Commits any pending changes, waits for the merging threads to finish and releases the directory lock.
+    ///
+    /// Afterwards every mutating method returns an error. Queries keep working against the last commit.
+    ///
+    /// Call this before the last handle to the index gets dropped. Otherwise a warning gets printed and nothing gets
+    /// committed on the way out: whatever wasn't committed yet is discarded, and merges that are still running are abandoned.
+    pub async fn close(&self) -> tantivy::Result<()> {
+        self.writer.close().await
     }
 
-    pub fn get_writer(&self) -> Arc<RwLock<Option<IndexWriter>>> {
-        self.writer_recycler.get_writer()
+    /// Start a transaction that applies several operations in one go and with one commit.
+    ///
+    /// Readers never observe the intermediate state of a transaction.
+    pub fn transaction(&self) -> Transaction<'_, M> {
+        Transaction::new(self)
+    }
+
+    pub async fn recycle_writer(&self) -> tantivy::Result<()> {
+        self.writer.replace_writer().await
+    }
+
+    /// Get notified whenever the `IndexWriter` gets recycled, whether by the recycle policy or explicitly
+    pub fn subscribe_recycle_events(&self) -> broadcast::Receiver<RecycleEvent> {
+        self.writer.subscribe()
     }
 
     /// Get the schema that this index uses
@@ -174,12 +339,23 @@ where
         M::schema()
     }
 
-    pub fn get_tantivy_backend(&self) -> TantivyBackend {
+    pub fn get_tantivy_backend(&self) -> TantivyBackend<'_> {
         TantivyBackend {
-            reader: &self.reader,
-            writer: &self.writer_recycler,
-            index: &self.index,
+            reader: self.searchable.reader(),
+            writer: &self.writer,
+            index: self.searchable.index(),
             schema: M::schema(),
         }
     }
 }
+
+impl<M> Deref for SearchIndex<M>
+where
+    M: entity_trait::Index,
+{
+    type Target = Searchable<M>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.searchable
+    }
+}
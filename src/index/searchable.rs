@@ -0,0 +1,162 @@
+use std::{collections::BTreeSet, marker::PhantomData, sync::Arc};
+
+use tantivy::{
+    query::{Query, QueryParser},
+    schema::{Field, Schema},
+    Index, IndexReader, TantivyError,
+};
+
+use crate::entity::entity_trait;
+
+use super::{
+    aggregation::{self, AggregationRequest, AggregationResponse},
+    blocking,
+    query::{
+        builder::QueryBuilder,
+        cursor::PinnedSearchers,
+        parser::{ExtQueryParser, QueryParserConfig},
+    },
+};
+
+/// The reading side of a search index, which `SearchIndex` and `ReadOnlySearchIndex` both dereference to.
+///
+/// Because all of the underlying data wraps an `Arc`, this can be negligibly cloned
+pub struct Searchable<M>
+where
+    M: entity_trait::Index,
+{
+    reader: IndexReader,
+    index: Arc<Index>,
+    /// The searchers that cursors refer to
+    pinned: PinnedSearchers,
+
+    phantom: PhantomData<M>,
+}
+
+impl<M> Clone for Searchable<M>
+where
+    M: entity_trait::Index,
+{
+    fn clone(&self) -> Self {
+        Self {
+            reader: self.reader.clone(),
+            index: Arc::clone(&self.index),
+            pinned: self.pinned.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<M> Searchable<M>
+where
+    M: entity_trait::Index,
+{
+    pub(crate) fn new(index: Arc<Index>, reader: IndexReader) -> Self {
+        Self {
+            reader,
+            index,
+            pinned: PinnedSearchers::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Fails with `TantivyError::SchemaError` if the index on disk was created with a different schema than the model has now.
+    ///
+    /// Such an index can't be read as the model, for example because a field that became fast has no column,
+    /// so it has to be rebuilt
+    pub(crate) fn check_schema(index: &Index) -> tantivy::Result<()> {
+        let expected = M::schema();
+        let found = index.schema();
+        if &found == expected {
+            return Ok(());
+        }
+        let changed: BTreeSet<&str> = changed_fields(expected, &found)
+            .chain(changed_fields(&found, expected))
+            .collect();
+        Err(TantivyError::SchemaError(format!(
+            "The index was created with a different schema than `{}` has now (changed fields: {}). \
+             It has to be rebuilt: delete it and add every model again",
+            std::any::type_name::<M>(),
+            changed.into_iter().collect::<Vec<_>>().join(", ")
+        )))
+    }
+
+    pub(crate) fn reader(&self) -> &IndexReader {
+        &self.reader
+    }
+
+    pub(crate) fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Get the query parser for this search index
+    pub fn query_parser(&self, default_fields: Vec<Field>) -> QueryParser {
+        QueryParser::for_index(&self.index, default_fields)
+    }
+
+    /// Get a query parser for text typed by end users, set up with typed fields, boosts and fuzziness.
+    ///
+    /// Fails with `TantivyError::InvalidArgument` if a fuzzy field allows more than 2 edits
+    pub fn typed_query_parser(
+        &self,
+        config: &QueryParserConfig,
+    ) -> tantivy::Result<ExtQueryParser> {
+        config.build(&self.index)
+    }
+
+    pub fn query<'a, Q>(&self, query: &'a Q, max_results: usize) -> QueryBuilder<'a, Q, M>
+    where
+        Q: Query + Sized,
+    {
+        let searcher = self.reader.searcher();
+        QueryBuilder::new(query, searcher, max_results).with_pinned(self.pinned.clone())
+    }
+
+    /// Run the aggregations of the request over every model that matches the query, for example to count models per value of a field.
+    ///
+    /// Pass `tantivy::query::AllQuery` to aggregate over the whole index
+    pub fn aggregate<Q>(
+        &self,
+        query: &Q,
+        request: &AggregationRequest,
+    ) -> tantivy::Result<AggregationResponse>
+    where
+        Q: Query,
+    {
+        aggregation::aggregate(&self.reader.searcher(), query, request)
+    }
+
+    /// Like `aggregate`, but runs on tokio's blocking thread pool
+    pub async fn aggregate_async<Q>(
+        &self,
+        query: &Q,
+        request: &AggregationRequest,
+    ) -> tantivy::Result<AggregationResponse>
+    where
+        Q: Query,
+    {
+        let searcher = self.reader.searcher();
+        let query = query.box_clone();
+        let request = request.clone();
+        blocking::run(move || aggregation::aggregate(&searcher, query.as_ref(), &request)).await
+    }
+
+    pub fn scored_doc_to_model(&self, doc: (f64, tantivy::DocAddress)) -> tantivy::Result<M> {
+        let searcher = self.reader.searcher();
+        let (score, address) = doc;
+
+        let doc = searcher.doc(address)?;
+
+        Ok(M::from_document(doc, score as f32))
+    }
+}
+
+/// The names of the fields of `schema` that `other` doesn't have, or has with different options
+fn changed_fields<'a>(schema: &'a Schema, other: &'a Schema) -> impl Iterator<Item = &'a str> {
+    schema.fields().filter_map(|(_, entry)| {
+        let same = other
+            .get_field(entry.name())
+            .is_ok_and(|field| other.get_field_entry(field) == entry);
+        (!same).then_some(entry.name())
+    })
+}
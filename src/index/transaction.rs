@@ -0,0 +1,176 @@
+use std::marker::PhantomData;
+
+use tantivy::{
+    query::{EnableScoring, Query},
+    schema::Schema,
+    IndexWriter, TantivyDocument, Term,
+};
+
+use crate::entity::entity_trait;
+
+use super::{search_index::SearchIndex, writer_recycler::estimated_document_size};
+
+pub(crate) enum Operation {
+    /// Replaces whatever document currently has the same primary key
+    Add {
+        key: Term,
+        doc: TantivyDocument,
+    },
+    Delete(Term),
+    DeleteQuery(Box<dyn Query>),
+}
+
+/// A group of operations that get applied to the search index as one unit.
+///
+/// Nothing touches the `IndexWriter` until `commit` is called, at which point every operation
+/// is handed to the writer task in one go and made visible by a single commit.
+///
+/// Dropping a `Transaction` without committing it discards all of its operations.
+///
+/// Example:
+/// ```ignore
+/// index
+///     .transaction()
+///     .add(&new_models)
+///     .remove(&old_models)
+///     .remove_by_terms(vec![MyModel::name_field().term(String::from("Joe"))])
+///     .commit()
+///     .await?;
+/// ```
+#[must_use = "a transaction does nothing unless `commit` is called"]
+pub struct Transaction<'a, M>
+where
+    M: entity_trait::Index,
+{
+    index: &'a SearchIndex<M>,
+    operations: Vec<Operation>,
+
+    phantom: PhantomData<M>,
+}
+
+impl<'a, M> Transaction<'a, M>
+where
+    M: entity_trait::Index,
+{
+    pub(crate) fn new(index: &'a SearchIndex<M>) -> Self {
+        Self {
+            index,
+            operations: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Adds the provided models, replacing any existing models with the same primary key.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, models: &[M]) -> Self {
+        for model in models {
+            self.operations.push(Operation::Add {
+                key: model.get_primary_key(),
+                doc: model.as_document(),
+            });
+        }
+        self
+    }
+
+    /// Removes the provided models by their primary key.
+    pub fn remove(mut self, models: &[M]) -> Self {
+        for model in models {
+            self.operations
+                .push(Operation::Delete(model.get_primary_key()));
+        }
+        self
+    }
+
+    /// Removes all models with the provided terms.
+    pub fn remove_by_terms(mut self, terms: Vec<Term>) -> Self {
+        for term in terms {
+            self.operations.push(Operation::Delete(term));
+        }
+        self
+    }
+
+    /// Removes all models that match the provided query.
+    pub fn remove_by_query(mut self, query: Box<dyn Query>) -> Self {
+        self.operations.push(Operation::DeleteQuery(query));
+        self
+    }
+
+    /// The number of operations queued up in this transaction
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Applies all of the operations and commits them.
+    ///
+    /// If any of the operations or the commit itself fail, the writer is rolled back to the last commit so that none
+    /// of the operations in this transaction become visible, now or with a later commit.
+    ///
+    /// Transactions that are queued up at the same time may share a commit, but each one is still all or nothing:
+    /// if one of them fails, the others get applied without it.
+    pub async fn commit(self) -> tantivy::Result<()> {
+        self.index.writer.apply(self.operations).await
+    }
+}
+
+impl Clone for Operation {
+    fn clone(&self) -> Self {
+        match self {
+            Operation::Add { key, doc } => Operation::Add {
+                key: key.clone(),
+                doc: doc.clone(),
+            },
+            Operation::Delete(term) => Operation::Delete(term.clone()),
+            Operation::DeleteQuery(query) => Operation::DeleteQuery(query.box_clone()),
+        }
+    }
+}
+
+impl Operation {
+    /// A rough estimate of how many bytes this operation takes up in the writer
+    pub(crate) fn estimated_size(&self) -> usize {
+        match self {
+            Operation::Add { doc, .. } => estimated_document_size(doc),
+            _ => 0,
+        }
+    }
+}
+
+/// Checks everything that can fail up front without touching the writer, which for now are
+/// delete queries that don't fit the schema
+pub(crate) fn validate_operations(
+    schema: &Schema,
+    operations: &[Operation],
+) -> tantivy::Result<()> {
+    for operation in operations {
+        if let Operation::DeleteQuery(query) = operation {
+            query.weight(EnableScoring::disabled_from_schema(schema))?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn apply_operations(
+    writer: &IndexWriter,
+    operations: Vec<Operation>,
+) -> tantivy::Result<()> {
+    for operation in operations {
+        match operation {
+            Operation::Add { key, doc } => {
+                // Delete by the primary key
+                writer.delete_term(key);
+                writer.add_document(doc)?;
+            }
+            Operation::Delete(term) => {
+                writer.delete_term(term);
+            }
+            Operation::DeleteQuery(query) => {
+                writer.delete_query(query)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,442 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use tantivy::{
+    collector::TopDocs, query::TermQuery, schema::IndexRecordOption, Index, IndexReader,
+    IndexWriter, ReloadPolicy, Searcher, TantivyDocument, TantivyError, Term,
+};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::util::async_retry;
+
+use super::{
+    blocking,
+    recycle_policy::{RecycleEvent, RecycleReason},
+    transaction::{apply_operations, validate_operations, Operation},
+    writer_recycler::{closed_error, estimated_document_size, IndexWriterRecycler},
+};
+
+type Reply<T> = oneshot::Sender<tantivy::Result<T>>;
+
+enum Command {
+    /// Operations that get applied and committed together with every other `Apply` that is queued up at the same time
+    Apply {
+        operations: Vec<Operation>,
+        entries: usize,
+        bytes: usize,
+        done: Reply<()>,
+    },
+    /// Runs on the writer task with exclusive access to the writer
+    Run(Box<dyn FnOnce(&mut WriterActor) + Send>),
+}
+
+/// A handle to the task that owns the `IndexWriter`.
+///
+/// Every write goes through a bounded queue, so callers wait for room in the queue rather than for a lock,
+/// and each of them gets their own completion future. Writes that are queued up at the same time get
+/// applied together, which lets tantivy index them in parallel, and then share a single commit.
+///
+/// Because all of the underlying data wraps an `Arc`, this can be negligibly cloned
+#[derive(Clone)]
+pub struct WriterHandle {
+    commands: mpsc::Sender<Command>,
+    events: broadcast::Sender<RecycleEvent>,
+    thread: Arc<WriterThread>,
+}
+
+impl WriterHandle {
+    /// Starts the writer task on its own thread
+    pub(crate) fn spawn(
+        recycler: IndexWriterRecycler,
+        reader: IndexReader,
+        reload_after_commit: bool,
+        queue_capacity: usize,
+    ) -> tantivy::Result<Self> {
+        if queue_capacity == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "the queue capacity must be at least 1".to_string(),
+            ));
+        }
+        let (commands, receiver) = mpsc::channel(queue_capacity);
+        let events = recycler.events();
+        let lookups = recycler
+            .index()
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let actor = WriterActor {
+            recycler,
+            reader,
+            lookups,
+            reload_after_commit,
+            commits: 0,
+        };
+        let thread = std::thread::Builder::new()
+            .name("tantivy-ext-writer".to_string())
+            .spawn(move || actor.run(receiver))?;
+        Ok(Self {
+            commands,
+            events,
+            thread: Arc::new(WriterThread(Mutex::new(Some(thread)))),
+        })
+    }
+
+    /// Applies the operations and commits them. If any of them fail, none of them become visible
+    pub(crate) async fn apply(&self, operations: Vec<Operation>) -> tantivy::Result<()> {
+        let entries = operations.len();
+        let bytes = operations.iter().map(Operation::estimated_size).sum();
+        let (done, result) = oneshot::channel();
+        self.send(Command::Apply {
+            operations,
+            entries,
+            bytes,
+            done,
+        })
+        .await?;
+        result.await.unwrap_or_else(|_| Err(closed_error()))
+    }
+
+    /// Runs the operation on the writer task. No other write happens until it returns
+    pub(crate) async fn run<T, F>(&self, operation: F) -> tantivy::Result<T>
+    where
+        F: FnOnce(&mut WriterActor) -> tantivy::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (done, result) = oneshot::channel();
+        self.send(Command::Run(Box::new(move |actor| {
+            // The caller may have given up waiting, which is fine
+            let _ = done.send(operation(actor));
+        })))
+        .await?;
+        result.await.unwrap_or_else(|_| Err(closed_error()))
+    }
+
+    /// Loads the latest stored document with the provided primary key and replaces it with whatever `replace` returns,
+    /// along with the primary key of the replacement.
+    ///
+    /// `replace` runs on the caller's side before the replacement gets sent to the writer task. If another write changed
+    /// the document in the meantime, the document gets loaded again and `replace` runs again on the fresh copy.
+    ///
+    /// Returns `None` if there is no document with that primary key.
+    pub(crate) async fn replace_by_key<T, F>(
+        &self,
+        key: Term,
+        mut replace: F,
+    ) -> tantivy::Result<Option<T>>
+    where
+        F: FnMut(f32, TantivyDocument) -> (T, Term, TantivyDocument),
+    {
+        loop {
+            let lookup = key.clone();
+            let (loaded, commits) = self
+                .run(move |actor| Ok((actor.load_latest_by_key(&lookup)?, actor.commits)))
+                .await?;
+            let Some((score, stored)) = loaded else {
+                return Ok(None);
+            };
+            let (value, new_key, doc) = replace(score, stored.clone());
+
+            let key = key.clone();
+            let replaced = self
+                .run(move |actor| {
+                    // Only a commit can change the stored document, and if there was one it may not have touched it
+                    if actor.commits != commits {
+                        let unchanged =
+                            actor.load_latest_by_key(&key)?.is_some_and(|(_, latest)| {
+                                latest.field_values() == stored.field_values()
+                            });
+                        if !unchanged {
+                            return Ok(false);
+                        }
+                    }
+                    let bytes = estimated_document_size(&doc);
+                    actor.replace_document([key, new_key], doc)?;
+                    actor.commit()?;
+                    actor.recycler.register_entries_processed(1, bytes)?;
+                    Ok(true)
+                })
+                .await?;
+            if replaced {
+                return Ok(Some(value));
+            }
+        }
+    }
+
+    pub(crate) async fn replace_writer(&self) -> tantivy::Result<()> {
+        self.run(|actor| actor.recycler.replace_writer(RecycleReason::Manual))
+            .await
+    }
+
+    /// Commits any pending changes, waits for the merging threads and releases the directory lock.
+    ///
+    /// The writer task stops right after, and this waits for its thread to finish
+    pub(crate) async fn close(&self) -> tantivy::Result<()> {
+        let closed = self.run(|actor| actor.close()).await;
+        // The queue only closes once the writer task has stopped, which means the index was closed, now or before
+        if closed.is_err() && !self.commands.is_closed() {
+            return closed;
+        }
+        self.thread.join().await
+    }
+
+    /// Get notified whenever the `IndexWriter` gets recycled
+    pub fn subscribe(&self) -> broadcast::Receiver<RecycleEvent> {
+        self.events.subscribe()
+    }
+
+    /// Waits for room in the queue, which is where the backpressure comes from
+    async fn send(&self, command: Command) -> tantivy::Result<()> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| closed_error())
+    }
+}
+
+/// The state of the writer task. Everything in here is only ever touched by that one thread
+pub(crate) struct WriterActor {
+    pub recycler: IndexWriterRecycler,
+    /// The reader queries go through, which only ever gets reloaded when the reload policy or read-your-writes say so
+    reader: IndexReader,
+    /// A reader of its own for the lookups writes need, so reloading it doesn't make commits visible to queries
+    lookups: IndexReader,
+    /// Whether the reader gets reloaded after every commit
+    reload_after_commit: bool,
+    /// How many commits went through, which tells whether anything could have changed since a document was loaded
+    commits: u64,
+}
+
+impl WriterActor {
+    pub fn writer(&mut self) -> tantivy::Result<&mut IndexWriter> {
+        self.recycler.writer()
+    }
+
+    pub fn index(&self) -> &Index {
+        self.recycler.index()
+    }
+
+    /// Attempt to commit all pending changes.
+    ///
+    /// This function will retry up to 3 times in case of errors. If every attempt fails, the writer is rolled back
+    /// to the last commit, as the pending changes would otherwise end up in whatever gets committed next.
+    ///
+    /// If the index was built with read-your-writes enabled, this only returns once the commit is visible to the reader.
+    pub fn commit(&mut self) -> tantivy::Result<()> {
+        let writer = self.writer()?;
+        let committed = async_retry::retry_with_backoff_blocking(
+            |_| writer.commit(),
+            3,
+            Duration::from_millis(100),
+        );
+        if let Err(err) = committed {
+            return self.rollback().and(Err(err));
+        }
+        self.commits += 1;
+        self.refresh_reader()
+    }
+
+    pub fn rollback(&mut self) -> tantivy::Result<()> {
+        self.recycler.rollback()
+    }
+
+    /// Clears the provided primary keys and adds the document in their place, rolling back if the add fails
+    fn replace_document<const N: usize>(
+        &mut self,
+        keys: [Term; N],
+        doc: TantivyDocument,
+    ) -> tantivy::Result<()> {
+        let writer = self.writer()?;
+        for key in keys {
+            writer.delete_term(key);
+        }
+        if let Err(err) = writer.add_document(doc) {
+            self.rollback()?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Reloads the reader if the index was built with read-your-writes enabled
+    pub fn refresh_reader(&self) -> tantivy::Result<()> {
+        if self.reload_after_commit {
+            self.reader.reload()?;
+        }
+        Ok(())
+    }
+
+    pub fn reload_reader(&self) -> tantivy::Result<()> {
+        self.reader.reload()
+    }
+
+    /// A searcher over the latest commit. Because nothing else can commit while the writer task is busy,
+    /// it stays up to date until the current operation commits
+    pub fn latest_searcher(&self) -> tantivy::Result<Searcher> {
+        self.lookups.reload()?;
+        Ok(self.lookups.searcher())
+    }
+
+    /// Looks up the stored document with the provided primary key in the latest commit
+    pub fn load_latest_by_key(
+        &self,
+        key: &Term,
+    ) -> tantivy::Result<Option<(f32, TantivyDocument)>> {
+        let searcher = self.latest_searcher()?;
+        let query = TermQuery::new(key.clone(), IndexRecordOption::Basic);
+        match searcher.search(&query, &TopDocs::with_limit(1))?.first() {
+            Some((score, address)) => Ok(Some((*score, searcher.doc(*address)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn close(&mut self) -> tantivy::Result<()> {
+        if self.recycler.is_closed() {
+            return Ok(());
+        }
+        self.commit()?;
+        self.recycler.close()
+    }
+
+    fn run(mut self, mut receiver: mpsc::Receiver<Command>) {
+        while let Some(command) = receiver.blocking_recv() {
+            let mut next = Some(command);
+            let mut batch = Vec::new();
+            // Gather every `Apply` that is already queued up, stopping at the first command that needs the writer to itself
+            while let Some(command) = next.take() {
+                match command {
+                    Command::Apply { .. } => {
+                        batch.push(command);
+                        next = receiver.try_recv().ok();
+                    }
+                    Command::Run(operation) => {
+                        self.apply_batch(std::mem::take(&mut batch));
+                        operation(&mut self);
+                    }
+                }
+            }
+            self.apply_batch(batch);
+            if self.recycler.is_closed() {
+                // Whatever is still queued up fails, as the queue gets dropped along with the writer task
+                return;
+            }
+        }
+        // Every handle is gone
+        self.shut_down();
+    }
+
+    /// Applies every transaction in the batch and commits them together.
+    ///
+    /// If a transaction fails half way through, the writer gets rolled back and the rest of the batch gets applied
+    /// again without it, so only that transaction gets the error. If the commit fails, every transaction gets the error
+    fn apply_batch(&mut self, batch: Vec<Command>) {
+        if batch.is_empty() {
+            return;
+        }
+        let schema = self.index().schema();
+        let mut pending = Vec::with_capacity(batch.len());
+        for command in batch {
+            let Command::Apply {
+                operations,
+                entries,
+                bytes,
+                done,
+            } = command
+            else {
+                unreachable!("only `Apply` commands get batched");
+            };
+            // A transaction that is known to fail is turned away before it can spoil the rest of the batch
+            match validate_operations(&schema, &operations) {
+                Ok(()) => pending.push(Pending {
+                    operations,
+                    entries,
+                    bytes,
+                    done,
+                }),
+                Err(err) => {
+                    let _ = done.send(Err(err));
+                }
+            }
+        }
+
+        while let Err((failed, err)) = self.apply_pending(&mut pending) {
+            let failed = pending.remove(failed);
+            if let Err(rollback_err) = self.rollback() {
+                // The writer is in an unknown state, so nothing in the batch can be trusted to get committed
+                let _ = failed.done.send(Err(err));
+                for transaction in pending {
+                    let _ = transaction.done.send(Err(rollback_err.clone()));
+                }
+                return;
+            }
+            let _ = failed.done.send(Err(err));
+        }
+        if pending.is_empty() {
+            return;
+        }
+
+        let entries = pending.iter().map(|transaction| transaction.entries).sum();
+        let bytes = pending.iter().map(|transaction| transaction.bytes).sum();
+        let outcome = self
+            .commit()
+            .and_then(|_| self.recycler.register_entries_processed(entries, bytes));
+        for transaction in pending {
+            let _ = transaction.done.send(outcome.clone());
+        }
+    }
+
+    /// Hands every pending transaction to the writer, stopping at the first one that fails.
+    ///
+    /// The operations are only moved out of a lone transaction, as there's nothing to apply again if it fails
+    fn apply_pending(&mut self, pending: &mut [Pending]) -> Result<(), (usize, TantivyError)> {
+        let lone = pending.len() == 1;
+        for (position, transaction) in pending.iter_mut().enumerate() {
+            let operations = if lone {
+                std::mem::take(&mut transaction.operations)
+            } else {
+                transaction.operations.clone()
+            };
+            self.writer()
+                .and_then(|writer| apply_operations(writer, operations))
+                .map_err(|err| (position, err))?;
+        }
+        Ok(())
+    }
+
+    /// Discards whatever was never committed and releases the directory lock if the index was never closed
+    fn shut_down(&mut self) {
+        if self.recycler.is_closed() {
+            return;
+        }
+        eprintln!("Warning: search index was dropped without calling `close`. Discarding uncommitted changes and abandoning running merges");
+        // Nobody is left to hear about a failure, and the writer gets dropped either way
+        let _ = self.rollback();
+        self.recycler.abandon();
+    }
+}
+
+/// A transaction of the batch that is being applied
+struct Pending {
+    operations: Vec<Operation>,
+    entries: usize,
+    bytes: usize,
+    done: Reply<()>,
+}
+
+/// The thread the writer task runs on.
+///
+/// It's only ever joined by `WriterHandle::close`. Dropping the last handle detaches it instead, so that
+/// dropping never blocks, and the writer task winds down on its own
+struct WriterThread(Mutex<Option<JoinHandle<()>>>);
+
+impl WriterThread {
+    async fn join(&self) -> tantivy::Result<()> {
+        let Some(thread) = self.0.lock().unwrap().take() else {
+            return Ok(());
+        };
+        blocking::run(move || thread.join()).await.map_err(|_| {
+            TantivyError::SystemError("the writer task of the search index panicked".to_string())
+        })
+    }
+}
@@ -1,64 +1,312 @@
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use tantivy::{Index, IndexWriter};
-use tokio::sync::RwLock;
+use tantivy::{
+    directory::{error::LockError, INDEX_WRITER_LOCK},
+    schema::OwnedValue,
+    Directory, Index, IndexWriter, TantivyDocument, TantivyError,
+};
+use tokio::sync::broadcast;
+
+use crate::util::async_retry;
+
+use super::{
+    index_builder::MEMORY_BUDGET_PER_THREAD_MIN,
+    merge_policy::IndexMergePolicy,
+    recycle_policy::{RecycleEvent, RecyclePolicy, RecycleReason, WriterStats},
+};
 
+/// How every `IndexWriter` the recycler creates should be set up
 #[derive(Clone)]
+pub struct WriterOptions {
+    pub mem_budget: usize,
+    /// If `None`, tantivy picks the number of indexing threads itself
+    pub num_threads: Option<usize>,
+    pub merge_policy: IndexMergePolicy,
+    /// How long to wait for another writer to release the directory lock
+    pub lock_timeout: Duration,
+    /// Whether the lock file may be deleted if the lock is still busy after `lock_timeout`
+    pub break_stale_lock: bool,
+}
+
+/// How long the backoff waits before the second attempt to take the directory lock, doubling from there
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Owns the `IndexWriter` and replaces it whenever the recycle policy says so.
+///
+/// Only the writer task touches this, so nothing in here needs a lock.
 pub struct IndexWriterRecycler {
     index: Arc<Index>,
-    writer: Arc<RwLock<Option<IndexWriter>>>, // Use Option for handling writer state
-    mem_budget: usize,
+    /// `None` once the index has been closed
+    writer: Option<IndexWriter>,
+    options: WriterOptions,
 
-    entries_processed: Arc<AtomicUsize>,
-    entries_before_recycle: usize,
+    policy: RecyclePolicy,
+    entries_processed: usize,
+    bytes_indexed: usize,
+    writer_created: Instant,
+    events: broadcast::Sender<RecycleEvent>,
 }
 
 impl IndexWriterRecycler {
-    pub fn new(index: Arc<Index>, mem_budget: usize, entries_before_recycle: usize) -> Self {
-        let writer = Arc::new(RwLock::new(Some(index.writer(mem_budget).unwrap())));
+    /// Waits with backoff for about `WriterOptions::lock_timeout` if another writer holds the directory lock,
+    /// sleeping the current thread in between attempts. The timeout is only checked in between attempts, so giving up
+    /// can take a little longer than that.
+    ///
+    /// Returns an error if the memory budget doesn't fit the number of threads or the writer can't be created
+    pub fn new(
+        index: Arc<Index>,
+        options: WriterOptions,
+        policy: RecyclePolicy,
+    ) -> tantivy::Result<Self> {
+        validate_options(&options)?;
+        let deadline = Instant::now() + options.lock_timeout;
+        // Only a busy lock is worth another attempt, so every other outcome ends the retries as an `Ok`
+        let attempts = async_retry::retry_with_backoff_blocking(
+            |_| match create_writer(&index, &options) {
+                Err(err) if is_lock_busy(&err) && Instant::now() < deadline => Err(err),
+                other => Ok(other),
+            },
+            usize::MAX,
+            LOCK_RETRY_DELAY,
+        )?;
+        let writer = match attempts {
+            Err(err) if is_lock_busy(&err) => after_lock_timeout(&index, &options)?,
+            writer => writer?,
+        };
+        Ok(Self::from_writer(index, writer, options, policy))
+    }
+
+    /// Like `new`, but sleeps the task rather than the thread in between attempts, and gives up right at the timeout
+    pub async fn new_waiting_for_lock(
+        index: Arc<Index>,
+        options: WriterOptions,
+        policy: RecyclePolicy,
+    ) -> tantivy::Result<Self> {
+        validate_options(&options)?;
+        let attempts = async_retry::retry_with_backoff(
+            |_| match create_writer(&index, &options) {
+                Err(err) if is_lock_busy(&err) => Err(err),
+                other => Ok(other),
+            },
+            usize::MAX,
+            LOCK_RETRY_DELAY,
+        );
+        let writer = match tokio::time::timeout(options.lock_timeout, attempts).await {
+            Ok(attempts) => attempts??,
+            Err(_) => after_lock_timeout(&index, &options)?,
+        };
+        Ok(Self::from_writer(index, writer, options, policy))
+    }
+
+    fn from_writer(
+        index: Arc<Index>,
+        writer: IndexWriter,
+        options: WriterOptions,
+        policy: RecyclePolicy,
+    ) -> Self {
+        let (events, _) = broadcast::channel(16);
         Self {
             index,
-            writer,
-            mem_budget,
-            entries_processed: Arc::new(AtomicUsize::new(0)),
-            entries_before_recycle,
+            writer: Some(writer),
+            options,
+            policy,
+            entries_processed: 0,
+            bytes_indexed: 0,
+            writer_created: Instant::now(),
+            events,
         }
     }
 
-    pub fn get_writer(&self) -> Arc<RwLock<Option<IndexWriter>>> {
-        Arc::clone(&self.writer)
+    /// Returns an error once the index has been closed
+    pub fn writer(&mut self) -> tantivy::Result<&mut IndexWriter> {
+        self.writer.as_mut().ok_or_else(closed_error)
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
     }
 
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_none()
+    }
+
+    /// Where `RecycleEvent`s get sent
+    pub fn events(&self) -> broadcast::Sender<RecycleEvent> {
+        self.events.clone()
+    }
+
+    /// Records that the writer processed `num` entries adding up to an estimated `bytes`, and recycles
+    /// the writer if the recycle policy says so.
+    ///
     /// Returns an error if the function tries to replace the `IndexWriter` but fails
-    pub async fn register_entries_processed(&self, num: usize) -> tantivy::Result<()> {
-        let entries = self.entries_processed.load(Ordering::Relaxed);
-        let new_num = entries + num;
-        if new_num > self.entries_before_recycle {
-            self.entries_processed.store(0, Ordering::Relaxed);
-            self.replace_writer().await?;
-        } else {
-            self.entries_processed
-                .store(entries + num, Ordering::Relaxed);
+    pub fn register_entries_processed(&mut self, num: usize, bytes: usize) -> tantivy::Result<()> {
+        self.entries_processed += num;
+        self.bytes_indexed += bytes;
+        match self.recycle_reason() {
+            Some(reason) => self.replace_writer(reason),
+            None => Ok(()),
         }
-        Ok(())
     }
 
-    pub async fn replace_writer(&self) -> tantivy::Result<()> {
-        let mut writer_lock = self.writer.write().await;
+    /// Start counting towards the next recycle from zero again
+    pub fn reset_entries_processed(&mut self) {
+        self.entries_processed = 0;
+        self.bytes_indexed = 0;
+    }
 
-        if let Some(old_writer) = writer_lock.take() {
-            // Wait for the old writer to clean up
-            println!("Recycling writer");
-            old_writer.wait_merging_threads()?;
-        }
+    pub fn replace_writer(&mut self, reason: RecycleReason) -> tantivy::Result<()> {
+        let Some(old_writer) = self.writer.take() else {
+            return Err(closed_error());
+        };
+        // Wait for the old writer to clean up
+        println!("Recycling writer");
+        old_writer.wait_merging_threads()?;
 
         // Now it is safe to create a new writer
-        let new_writer = self.index.writer(self.mem_budget)?;
-        *writer_lock = Some(new_writer);
+        self.writer = Some(create_writer(&self.index, &self.options)?);
 
+        let created = std::mem::replace(&mut self.writer_created, Instant::now());
+        // Nobody may be listening, which is fine
+        let _ = self.events.send(RecycleEvent {
+            reason,
+            entries_processed: std::mem::take(&mut self.entries_processed),
+            bytes_indexed: std::mem::take(&mut self.bytes_indexed),
+            writer_age: created.elapsed(),
+        });
         Ok(())
     }
+
+    /// Discards every change since the last commit.
+    ///
+    /// tantivy rolls back by swapping in a brand new writer, which comes with its default merge policy,
+    /// so the configured one gets set on it again
+    pub fn rollback(&mut self) -> tantivy::Result<()> {
+        let merge_policy = self.options.merge_policy.to_tantivy();
+        let writer = self.writer()?;
+        writer.rollback()?;
+        writer.set_merge_policy(merge_policy);
+        Ok(())
+    }
+
+    /// Drops the writer without waiting for the merging threads, which releases the directory lock.
+    /// No new writer gets created afterwards
+    pub fn abandon(&mut self) {
+        self.writer = None;
+    }
+
+    /// Waits for the merging threads and releases the directory lock. No new writer gets created afterwards
+    pub fn close(&mut self) -> tantivy::Result<()> {
+        match self.writer.take() {
+            Some(writer) => writer.wait_merging_threads(),
+            None => Ok(()),
+        }
+    }
+
+    fn stats(&self) -> WriterStats {
+        WriterStats {
+            entries_processed: self.entries_processed,
+            bytes_indexed: self.bytes_indexed,
+            writer_age: self.writer_created.elapsed(),
+        }
+    }
+
+    fn recycle_reason(&self) -> Option<RecycleReason> {
+        let segment_count = || {
+            self.index
+                .searchable_segment_ids()
+                .map(|ids| ids.len())
+                .unwrap_or(0)
+        };
+        self.policy.check(&self.stats(), &segment_count)
+    }
+}
+
+pub(crate) fn closed_error() -> TantivyError {
+    TantivyError::SystemError("the search index has been closed".to_string())
+}
+
+fn create_writer(index: &Index, options: &WriterOptions) -> tantivy::Result<IndexWriter> {
+    let writer: IndexWriter = match options.num_threads {
+        Some(num_threads) => index.writer_with_num_threads(num_threads, options.mem_budget)?,
+        None => index.writer(options.mem_budget)?,
+    };
+    writer.set_merge_policy(options.merge_policy.to_tantivy());
+    Ok(writer)
+}
+
+fn is_lock_busy(err: &TantivyError) -> bool {
+    matches!(err, TantivyError::LockFailure(LockError::LockBusy, _))
+}
+
+/// What happens once the directory lock is still busy after the lock timeout: the lock gets broken if stale lock
+/// recovery is enabled, otherwise building the index fails with `LockBusy`
+fn after_lock_timeout(index: &Index, options: &WriterOptions) -> tantivy::Result<IndexWriter> {
+    if !options.break_stale_lock {
+        return Err(TantivyError::LockFailure(
+            LockError::LockBusy,
+            Some(format!(
+                "timed out after {:?} waiting for another `IndexWriter` to release the directory lock",
+                options.lock_timeout
+            )),
+        ));
+    }
+    break_stale_lock(index)?;
+    create_writer(index, options)
+}
+
+/// Deletes the writer lock file. This is only safe if whoever took the lock is known to be gone,
+/// otherwise two writers end up working on the same index
+fn break_stale_lock(index: &Index) -> tantivy::Result<()> {
+    eprintln!(
+        "Warning: breaking the stale writer lock at {:?}",
+        INDEX_WRITER_LOCK.filepath
+    );
+    index
+        .directory()
+        .delete(&INDEX_WRITER_LOCK.filepath)
+        .map_err(|err| {
+            TantivyError::SystemError(format!("could not break the writer lock: {}", err))
+        })
+}
+
+fn validate_options(options: &WriterOptions) -> tantivy::Result<()> {
+    match options.num_threads {
+        Some(num_threads) => validate_memory_budget(options.mem_budget, num_threads),
+        None => Ok(()),
+    }
+}
+
+/// The memory budget gets split evenly between the indexing threads,
+/// and every thread needs at least `MEMORY_BUDGET_PER_THREAD_MIN` bytes
+fn validate_memory_budget(mem_budget: usize, num_threads: usize) -> tantivy::Result<()> {
+    if num_threads == 0 {
+        return Err(TantivyError::InvalidArgument(
+            "the number of indexing threads must be at least 1".to_string(),
+        ));
+    }
+    if mem_budget / num_threads < MEMORY_BUDGET_PER_THREAD_MIN {
+        return Err(TantivyError::InvalidArgument(format!(
+            "a memory budget of {} bytes is too small for {} indexing threads: each thread needs at least {} bytes, so the budget must be at least {} bytes",
+            mem_budget,
+            num_threads,
+            MEMORY_BUDGET_PER_THREAD_MIN,
+            MEMORY_BUDGET_PER_THREAD_MIN * num_threads
+        )));
+    }
+    Ok(())
+}
+
+/// How many bytes a document counts for towards `RecyclePolicy::BytesIndexed`
+pub(crate) fn estimated_document_size(doc: &TantivyDocument) -> usize {
+    doc.field_values()
+        .iter()
+        .map(|field_value| match field_value.value() {
+            OwnedValue::Str(text) => text.len(),
+            OwnedValue::Bytes(bytes) => bytes.len(),
+            _ => std::mem::size_of::<u64>(),
+        })
+        .sum()
 }
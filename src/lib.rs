@@ -8,4 +8,24 @@ pub use entity::field::*;
 pub use ext_index_macro::TantivySearchIndex;
 pub use index::ext::*;
 pub use index::search_index::SearchIndex;
+pub use index::read_only::ReadOnlySearchIndex;
+pub use index::searchable::Searchable;
+pub use index::index_builder::MEMORY_BUDGET_PER_THREAD_MIN;
+pub use index::ext::ext_query::ExtQuery;
+pub use index::query::bool_query::BoolQueryBuilder;
+pub use index::query::cursor::{Cursor, Page};
+pub use index::query::results::{Highlight, Hit, SearchResults};
+pub use index::query::parser::{ExtQueryParser, Fuzziness, QueryParserConfig};
+pub use index::aggregation::{
+    AggregationHandle, AggregationRequest, AggregationResponse, DateHistogramBucket,
+    DateHistogramResult, StatsResult, TermsBucket, TermsResult,
+};
+pub use index::query::score_functions::{Decay, DecayCurve, FieldValueFactor, Modifier};
+pub use index::query::sort::SortValue;
+pub use tantivy::Order;
+pub use index::transaction::Transaction;
+pub use index::patch::Patch;
+pub use index::ingest::{IngestPolicy, IngestSummary};
+pub use index::merge_policy::IndexMergePolicy;
+pub use index::recycle_policy::{RecycleEvent, RecyclePolicy, RecycleReason};
 pub use util::*;
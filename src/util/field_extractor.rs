@@ -42,4 +42,15 @@ pub fn field_as_f64(schema: &Schema, doc: &TantivyDocument, field_name: &str)->O
         }
     }
     None
-}
\ No newline at end of file
+}
+
+pub fn field_as_i64(schema: &Schema, doc: &TantivyDocument, field_name: &str)->Option<i64>{
+    for (field, value) in doc.iter_fields_and_values() {
+        if schema.get_field_name(field) == field_name {
+            if let OwnedValue::I64(val) = value {
+                return Some(*val)
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{ram_index, Doc};
+use tantivy::{query::AllQuery, DateTime, TantivyError};
+use tantivy_ext::{AggregationRequest, SearchIndex};
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    index
+        .add(&[
+            Doc::new("a", "first")
+                .category("reports")
+                .date_secs(0)
+                .views(10)
+                .popularity(1.0),
+            Doc::new("b", "second")
+                .category("reports")
+                .date_secs(150)
+                .views(20)
+                .popularity(2.0),
+            Doc::new("c", "third")
+                .category("reviews")
+                .date_secs(320)
+                .views(30)
+                .popularity(4.5),
+        ])
+        .await
+        .unwrap();
+    index
+        .add(&[Doc::new("d", "fourth")
+            .category("drafts")
+            .date_secs(360)
+            .views(40)
+            .popularity(0.5)])
+        .await
+        .unwrap();
+    index
+}
+
+#[tokio::test]
+async fn counts_models_per_value_of_a_fast_str_field() {
+    let index = index().await;
+    let mut request = AggregationRequest::new();
+    let all = request.terms(Doc::category_field(), 10);
+    let top = request.terms(Doc::category_field(), 1);
+
+    let response = index.aggregate(&AllQuery, &request).unwrap();
+
+    let buckets: Vec<_> = response
+        .get(&all)
+        .unwrap()
+        .buckets
+        .into_iter()
+        .map(|bucket| (bucket.key, bucket.doc_count))
+        .collect();
+    assert_eq!(buckets[0], ("reports".to_string(), 2));
+    let mut rest = buckets[1..].to_vec();
+    rest.sort();
+    assert_eq!(
+        rest,
+        [("drafts".to_string(), 1), ("reviews".to_string(), 1)]
+    );
+
+    let top = response.get(&top).unwrap();
+    assert_eq!(top.buckets.len(), 1);
+    assert_eq!(top.buckets[0].key, "reports");
+    assert_eq!(top.sum_other_doc_count, 2);
+}
+
+#[tokio::test]
+async fn counts_models_per_date_interval() {
+    let index = index().await;
+    let mut request = AggregationRequest::new();
+    let per_100s = request.date_histogram(Doc::date_field(), Duration::from_secs(100));
+
+    let response = index.aggregate(&AllQuery, &request).unwrap();
+
+    let buckets: Vec<_> = response
+        .get(&per_100s)
+        .unwrap()
+        .buckets
+        .into_iter()
+        .map(|bucket| (bucket.start, bucket.doc_count))
+        .collect();
+    assert_eq!(
+        buckets,
+        [
+            (DateTime::from_timestamp_secs(0), 1),
+            (DateTime::from_timestamp_secs(100), 1),
+            (DateTime::from_timestamp_secs(200), 0),
+            (DateTime::from_timestamp_secs(300), 2),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn computes_stats_over_the_matching_models() {
+    let index = index().await;
+    let mut request = AggregationRequest::new();
+    let views = request.stats(Doc::views_field());
+    let popularity = request.stats(Doc::popularity_field());
+
+    let response = index.aggregate(&AllQuery, &request).unwrap();
+    let stats = response.get(&views).unwrap();
+    assert_eq!(stats.count, 4);
+    assert_eq!(stats.sum, 100.0);
+    assert_eq!(stats.min, Some(10.0));
+    assert_eq!(stats.max, Some(40.0));
+    assert_eq!(stats.avg, Some(25.0));
+    assert_eq!(response.get(&popularity).unwrap().max, Some(4.5));
+
+    let reports = index
+        .aggregate(&Doc::category_field().eq("reports"), &request)
+        .unwrap();
+    assert_eq!(reports.get(&views).unwrap().sum, 30.0);
+
+    let nothing = index
+        .aggregate_async(&Doc::category_field().eq("missing"), &request)
+        .await
+        .unwrap();
+    let stats = nothing.get(&views).unwrap();
+    assert_eq!(stats.count, 0);
+    assert_eq!(stats.min, None);
+    assert_eq!(stats.avg, None);
+}
+
+#[tokio::test]
+async fn handles_only_read_the_response_of_their_own_request() {
+    let index = index().await;
+    let mut other = AggregationRequest::new();
+    other.stats(Doc::views_field());
+    let foreign = other.terms(Doc::category_field(), 10);
+
+    let response = index
+        .aggregate(&AllQuery, &AggregationRequest::new())
+        .unwrap();
+    assert!(matches!(
+        response.get(&foreign),
+        Err(TantivyError::InvalidArgument(_))
+    ));
+}
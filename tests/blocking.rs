@@ -0,0 +1,53 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{ids, ram_index, Doc};
+use tantivy::query::AllQuery;
+use tantivy_ext::AggregationRequest;
+
+#[tokio::test]
+async fn async_searches_match_their_blocking_counterparts() {
+    let index = ram_index();
+    index
+        .add(&[
+            Doc::new("a", "first").views(3),
+            Doc::new("b", "second").views(5),
+        ])
+        .await
+        .unwrap();
+
+    let blocking = index.query(&AllQuery, 10).execute().unwrap();
+    let non_blocking = index.query(&AllQuery, 10).execute_async().await.unwrap();
+    assert_eq!(ids(&blocking), ids(&non_blocking));
+
+    let mut request = AggregationRequest::new();
+    let stats = request.stats(Doc::views_field());
+    let response = index.aggregate_async(&AllQuery, &request).await.unwrap();
+    assert_eq!(response.get(&stats).unwrap().sum, 8.0);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn writes_leave_the_runtime_free_for_other_tasks() {
+    let index = ram_index();
+    let ticker = tokio::spawn(async {
+        let mut ticks = 0;
+        for _ in 0..5 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            ticks += 1;
+        }
+        ticks
+    });
+
+    for id in 0..20 {
+        index
+            .add(&[Doc::new(&id.to_string(), "title")])
+            .await
+            .unwrap();
+    }
+
+    // With a single worker thread, the ticker only gets to run in between commits because they don't block it
+    assert!(ticker.is_finished());
+    assert_eq!(ticker.await.unwrap(), 5);
+    assert_eq!(index.query(&AllQuery, 100).execute().unwrap().len(), 20);
+}
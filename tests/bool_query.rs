@@ -0,0 +1,117 @@
+mod common;
+
+use common::{matching, ram_index, Doc};
+use tantivy_ext::{BoolQueryBuilder, SearchIndex};
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    index
+        .add(&[
+            Doc::new("a", "annual report").category("reports").views(10),
+            Doc::new("b", "annual review").category("reviews").views(20),
+            Doc::new("c", "quarterly report")
+                .category("reports")
+                .views(30),
+            Doc::new("d", "draft").category("drafts").views(40),
+        ])
+        .await
+        .unwrap();
+    index
+}
+
+#[tokio::test]
+async fn operators_combine_queries() {
+    let index = index().await;
+    let title = Doc::title_field();
+    let category = Doc::category_field();
+
+    let both = title.prefix("annual") & category.eq("reports");
+    assert_eq!(matching(&index, &both), ["a"]);
+
+    let either = category.eq("drafts") | category.eq("reviews");
+    assert_eq!(matching(&index, &either), ["b", "d"]);
+
+    let neither = !category.eq("reports");
+    assert_eq!(matching(&index, &neither), ["b", "d"]);
+
+    let nested = (title.prefix("report") | category.eq("drafts")) & !Doc::views_field().eq(30);
+    assert_eq!(matching(&index, &nested), ["a", "d"]);
+}
+
+#[tokio::test]
+async fn must_and_must_not_narrow_the_results() {
+    let index = index().await;
+
+    let query = BoolQueryBuilder::new()
+        .must(Doc::title_field().prefix("report"))
+        .must_not(Doc::views_field().range(20..));
+    assert_eq!(matching(&index, &query), ["a"]);
+}
+
+#[tokio::test]
+async fn only_exclusions_match_everything_else() {
+    let index = index().await;
+
+    let query = BoolQueryBuilder::new().must_not(Doc::category_field().eq("reports"));
+    assert_eq!(matching(&index, &query), ["b", "d"]);
+}
+
+#[tokio::test]
+async fn should_clauses_alone_need_one_match() {
+    let index = index().await;
+
+    let query = BoolQueryBuilder::new()
+        .should(Doc::category_field().eq("drafts"))
+        .should(Doc::views_field().eq(10));
+    assert_eq!(matching(&index, &query), ["a", "d"]);
+}
+
+#[tokio::test]
+async fn minimum_should_match_applies_next_to_must_clauses() {
+    let index = index().await;
+    let title = Doc::title_field();
+
+    let query = BoolQueryBuilder::new()
+        .filter(Doc::views_field().range(..40))
+        .should(title.prefix("annual"))
+        .should(title.prefix("report"))
+        .should(Doc::category_field().eq("reports"))
+        .minimum_should_match(2);
+    assert_eq!(matching(&index, &query), ["a", "c"]);
+
+    // Without a minimum, should clauses only add to the score next to a filter
+    let query = BoolQueryBuilder::new()
+        .filter(Doc::views_field().range(..40))
+        .should(title.prefix("annual"));
+    assert_eq!(matching(&index, &query), ["a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn filters_do_not_add_to_the_score() {
+    let index = index().await;
+
+    let query = BoolQueryBuilder::new().filter(Doc::title_field().prefix("annual"));
+    let results = index.query(&query, 10).execute_results().unwrap();
+    assert_eq!(results.total_hits, 2);
+    assert!(results.hits.iter().all(|hit| hit.score == 0.0));
+}
+
+#[tokio::test]
+async fn boost_multiplies_the_score() {
+    let index = index().await;
+    let plain = BoolQueryBuilder::new().must(Doc::title_field().phrase("draft"));
+    let boosted = plain.clone().boost(2.0);
+
+    let plain = index.query(&plain, 1).execute_results().unwrap();
+    let boosted = index.query(&boosted, 1).execute_results().unwrap();
+    assert_eq!(boosted.hits[0].score, plain.hits[0].score * 2.0);
+}
+
+#[tokio::test]
+async fn builders_can_be_combined_further() {
+    let index = index().await;
+
+    let reports = BoolQueryBuilder::new().must(Doc::category_field().eq("reports"));
+    let query = reports.build() & Doc::views_field().range(20..);
+    assert_eq!(matching(&index, &query), ["c"]);
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::{all_ids, ram_builder, ram_index, Doc};
+use tantivy::{query::AllQuery, ReloadPolicy};
+
+#[tokio::test]
+async fn clear_removes_every_model() {
+    let index = ram_index();
+    index
+        .add(&[Doc::new("a", "first"), Doc::new("b", "second")])
+        .await
+        .unwrap();
+
+    index.clear().await.unwrap();
+
+    assert!(all_ids(&index).is_empty());
+    index.add(&[Doc::new("c", "third")]).await.unwrap();
+    assert_eq!(all_ids(&index), ["c"]);
+}
+
+#[tokio::test]
+async fn clear_is_visible_right_away_without_read_your_writes() {
+    let index = ram_builder()
+        .with_reload_policy(ReloadPolicy::Manual)
+        .build();
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+    index.clear().await.unwrap();
+
+    assert!(index.query(&AllQuery, 10).execute().unwrap().is_empty());
+}
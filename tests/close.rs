@@ -0,0 +1,47 @@
+mod common;
+
+use common::{all_ids, builder_in, ram_index, ram_index_in, Doc};
+use std::time::Duration;
+
+use tantivy::directory::RamDirectory;
+
+#[tokio::test]
+async fn writes_fail_after_close_but_queries_keep_working() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    index.close().await.unwrap();
+
+    assert!(index.add(&[Doc::new("b", "second")]).await.is_err());
+    assert_eq!(all_ids(&index), ["a"]);
+    // Closing again is fine
+    index.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn close_releases_the_directory_lock() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    let busy = builder_in(directory.clone())
+        .with_lock_timeout(Duration::from_millis(50))
+        .try_build();
+    assert!(busy.is_err());
+    index.close().await.unwrap();
+
+    let reopened = builder_in(directory).try_build().unwrap();
+    assert_eq!(all_ids(&reopened), ["a"]);
+}
+
+#[tokio::test]
+async fn dropping_without_close_keeps_what_was_committed() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    drop(index);
+
+    let reopened = builder_in(directory).build_async().await.unwrap();
+    assert_eq!(all_ids(&reopened), ["a"]);
+}
@@ -0,0 +1,186 @@
+#![allow(dead_code)]
+
+use std::{
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use tantivy::{
+    directory::{
+        error::{DeleteError, OpenReadError, OpenWriteError},
+        FileHandle, RamDirectory, WatchCallback, WatchHandle, WritePtr,
+    },
+    query::{AllQuery, Query},
+    DateTime, Directory,
+};
+use tantivy_ext::{index::index_builder::SearchIndexBuilder, SearchIndex, TantivySearchIndex};
+
+#[derive(TantivySearchIndex, Debug, Clone)]
+pub struct Doc {
+    #[tantivy_ext("primary_key")]
+    pub id: tantivy_ext::FastStr,
+    pub title: tantivy_ext::Tokenized,
+    pub tag: tantivy_ext::Str,
+    pub category: tantivy_ext::FastStr,
+    pub date: tantivy_ext::Date,
+    pub popularity: tantivy_ext::FastF64,
+    pub views: tantivy_ext::FastU64,
+    pub rank: tantivy_ext::FastI64,
+    pub score: tantivy_ext::Score,
+    pub sort_values: tantivy_ext::SortValues,
+}
+
+impl Doc {
+    pub fn new(id: &str, title: &str) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            tag: "".into(),
+            category: "".into(),
+            date: DateTime::from_timestamp_secs(0).into(),
+            popularity: 0.0.into(),
+            views: 0.into(),
+            rank: 0.into(),
+            score: 0.0.into(),
+            sort_values: Default::default(),
+        }
+    }
+
+    pub fn id(&self) -> String {
+        tantivy_ext::Field::tantivy_val(&self.id)
+    }
+
+    pub fn title(&self) -> String {
+        tantivy_ext::Field::tantivy_val(&self.title)
+    }
+
+    pub fn category(mut self, category: &str) -> Self {
+        self.category = category.into();
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = tag.into();
+        self
+    }
+
+    pub fn date_secs(mut self, secs: i64) -> Self {
+        self.date = DateTime::from_timestamp_secs(secs).into();
+        self
+    }
+
+    pub fn popularity(mut self, popularity: f64) -> Self {
+        self.popularity = popularity.into();
+        self
+    }
+
+    pub fn views(mut self, views: u64) -> Self {
+        self.views = views.into();
+        self
+    }
+
+    pub fn rank(mut self, rank: i64) -> Self {
+        self.rank = rank.into();
+        self
+    }
+}
+
+/// An index in a fresh `RamDirectory` whose writes are visible to queries as soon as they return
+pub fn ram_index() -> SearchIndex<Doc> {
+    ram_index_in(RamDirectory::create())
+}
+
+pub fn ram_index_in<D>(directory: D) -> SearchIndex<Doc>
+where
+    D: Directory,
+{
+    builder_in(directory).with_read_your_writes(true).build()
+}
+
+/// A builder for an index in a fresh `RamDirectory`, with the default settings
+pub fn ram_builder() -> SearchIndexBuilder<Doc> {
+    builder_in(RamDirectory::create())
+}
+
+pub fn builder_in<D>(directory: D) -> SearchIndexBuilder<Doc>
+where
+    D: Directory,
+{
+    Doc::index_builder("unused".into()).with_directory(directory)
+}
+
+/// The ids of every model in the index, sorted
+pub fn all_ids(index: &SearchIndex<Doc>) -> Vec<String> {
+    let mut ids = ids(&index.query(&AllQuery, 10_000).execute().unwrap());
+    ids.sort();
+    ids
+}
+
+/// The ids of every model that matches the query, sorted
+pub fn matching<Q>(index: &SearchIndex<Doc>, query: &Q) -> Vec<String>
+where
+    Q: Query,
+{
+    let mut ids = ids(&index.query(query, 10_000).execute().unwrap());
+    ids.sort();
+    ids
+}
+
+/// The ids of the models, in order
+pub fn ids(docs: &[Doc]) -> Vec<String> {
+    docs.iter().map(Doc::id).collect()
+}
+
+/// A `RamDirectory` whose commits can be made to fail on demand, by failing to write `meta.json`
+#[derive(Clone, Debug, Default)]
+pub struct FailingDirectory {
+    inner: RamDirectory,
+    fail_commits: Arc<AtomicBool>,
+}
+
+impl FailingDirectory {
+    pub fn fail_commits(&self, fail: bool) {
+        self.fail_commits.store(fail, Ordering::SeqCst);
+    }
+}
+
+impl Directory for FailingDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>, OpenReadError> {
+        self.inner.get_file_handle(path)
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        self.inner.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.inner.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        self.inner.open_write(path)
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        self.inner.atomic_read(path)
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        if path == Path::new("meta.json") && self.fail_commits.load(Ordering::SeqCst) {
+            return Err(io::Error::other("injected commit failure"));
+        }
+        self.inner.atomic_write(path, data)
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.inner.sync_directory()
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> tantivy::Result<WatchHandle> {
+        self.inner.watch(watch_callback)
+    }
+}
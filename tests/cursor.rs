@@ -0,0 +1,214 @@
+mod common;
+
+use common::{ids, ram_index, Doc};
+use tantivy::{query::AllQuery, Order, TantivyError};
+use tantivy_ext::{Cursor, SearchIndex};
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    let docs: Vec<_> = (1..=5)
+        .map(|i| {
+            Doc::new(&i.to_string(), "model")
+                .views(i)
+                .popularity(i as f64)
+        })
+        .collect();
+    index.add(&docs).await.unwrap();
+    index
+}
+
+/// Follows the cursors from `first` on, collecting the ids of every page
+fn follow(index: &SearchIndex<Doc>, first: Option<Cursor>) -> Vec<String> {
+    let mut seen = Vec::new();
+    let mut next = first;
+    while let Some(cursor) = next {
+        let page = index
+            .query(&AllQuery, 2)
+            .order_by(Doc::views_field(), Order::Desc)
+            .after(cursor)
+            .execute_page()
+            .unwrap();
+        seen.extend(ids(&page.models));
+        next = page.next;
+    }
+    seen
+}
+
+#[tokio::test]
+async fn pages_do_not_shift_when_models_are_committed_in_between() {
+    let index = index().await;
+
+    let page = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::views_field(), Order::Desc)
+        .execute_page()
+        .unwrap();
+    assert_eq!(ids(&page.models), ["5", "4"]);
+
+    // Would come first, and push every other model one place down
+    index
+        .add(&[Doc::new("new", "model").views(100)])
+        .await
+        .unwrap();
+
+    assert_eq!(follow(&index, page.next), ["3", "2", "1"]);
+
+    let fresh = index
+        .query(&AllQuery, 1)
+        .order_by(Doc::views_field(), Order::Desc)
+        .execute()
+        .unwrap();
+    assert_eq!(ids(&fresh), ["new"]);
+}
+
+#[tokio::test]
+async fn pages_by_score_and_offset() {
+    let index = index().await;
+
+    let first = index.query(&AllQuery, 2).execute_page().unwrap();
+    let second = index
+        .query(&AllQuery, 2)
+        .after(first.next.unwrap())
+        .execute_page()
+        .unwrap();
+    let third = index
+        .query(&AllQuery, 2)
+        .after(second.next.unwrap())
+        .execute_page()
+        .unwrap();
+    assert!(third.next.is_none());
+
+    let mut seen = ids(&first.models);
+    seen.extend(ids(&second.models));
+    seen.extend(ids(&third.models));
+    seen.sort();
+    assert_eq!(seen, ["1", "2", "3", "4", "5"]);
+
+    let offset = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::views_field(), Order::Asc)
+        .offset(3)
+        .execute()
+        .unwrap();
+    assert_eq!(ids(&offset), ["4", "5"]);
+}
+
+#[tokio::test]
+async fn cursors_round_trip_through_tokens() {
+    let index = index().await;
+
+    let page = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::views_field(), Order::Desc)
+        .execute_page()
+        .unwrap();
+    let cursor = page.next.unwrap();
+    let token = cursor.encode();
+    assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+
+    let decoded = Cursor::decode(&token).unwrap();
+    assert_eq!(decoded, cursor);
+    assert_eq!(follow(&index, Some(decoded)), ["3", "2", "1"]);
+}
+
+#[test]
+fn invalid_tokens_are_rejected() {
+    for token in ["", "zz", "abc", "7b7d", "é"] {
+        assert!(
+            matches!(Cursor::decode(token), Err(TantivyError::InvalidArgument(_))),
+            "{:?}",
+            token
+        );
+    }
+}
+
+#[tokio::test]
+async fn cursors_expire_once_their_generation_is_dropped() {
+    let index = index().await;
+    let first = index.query(&AllQuery, 2).execute_page().unwrap();
+    let expiring = first.next.unwrap();
+
+    // Every commit makes a new generation, and every page that has a cursor keeps its generation around
+    for i in 0..8 {
+        index
+            .add(&[Doc::new(&format!("more{}", i), "model")])
+            .await
+            .unwrap();
+        let page = index.query(&AllQuery, 2).execute_page().unwrap();
+        assert!(page.next.is_some());
+    }
+
+    let result = index.query(&AllQuery, 2).after(expiring).execute_page();
+    match result {
+        Err(TantivyError::InvalidArgument(message)) => assert!(message.contains("expired")),
+        other => panic!(
+            "expected the cursor to have expired, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[tokio::test]
+async fn searches_without_a_cursor_keep_no_generation_around() {
+    let index = index().await;
+    let page = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::views_field(), Order::Desc)
+        .execute_page()
+        .unwrap();
+
+    // Every search fills a whole page on a new generation, but none of them hands out a cursor
+    for i in 0..10 {
+        index
+            .add(&[Doc::new(&format!("more{}", i), "model")])
+            .await
+            .unwrap();
+        assert_eq!(index.query(&AllQuery, 2).execute().unwrap().len(), 2);
+        let models = index.query(&AllQuery, 2).execute_async().await.unwrap();
+        assert_eq!(models.len(), 2);
+    }
+
+    assert_eq!(follow(&index, page.next), ["3", "2", "1"]);
+}
+
+#[tokio::test]
+async fn cursors_only_work_with_searches_ordered_the_same_way() {
+    let index = index().await;
+    let by_views = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::views_field(), Order::Desc)
+        .execute_page()
+        .unwrap()
+        .next
+        .unwrap();
+    let by_score = index
+        .query(&AllQuery, 2)
+        .execute_page()
+        .unwrap()
+        .next
+        .unwrap();
+
+    let other_field = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::popularity_field(), Order::Desc)
+        .after(by_views.clone())
+        .execute_page();
+    assert!(matches!(other_field, Err(TantivyError::InvalidArgument(_))));
+
+    let other_order = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::views_field(), Order::Asc)
+        .after(by_views.clone())
+        .execute_page();
+    assert!(matches!(other_order, Err(TantivyError::InvalidArgument(_))));
+
+    let unordered = index.query(&AllQuery, 2).after(by_views).execute_page();
+    assert!(matches!(unordered, Err(TantivyError::InvalidArgument(_))));
+
+    let ordered = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::views_field(), Order::Desc)
+        .after(by_score)
+        .execute_page();
+    assert!(matches!(ordered, Err(TantivyError::InvalidArgument(_))));
+}
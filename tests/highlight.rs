@@ -0,0 +1,144 @@
+mod common;
+
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use common::{ids, ram_index, Doc};
+use tantivy::{
+    query::{EnableScoring, Query, Weight},
+    schema::Term,
+};
+use tantivy_ext::SearchIndex;
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    index
+        .add(&[
+            Doc::new("a", "The annual report of <the> company"),
+            Doc::new("b", "Nothing to see here, but a report"),
+        ])
+        .await
+        .unwrap();
+    index
+}
+
+/// Counts how often the terms of the query are looked up, which is what building a snippet generator starts with
+#[derive(Clone, Debug)]
+struct CountingQuery {
+    inner: Arc<dyn Query>,
+    term_lookups: Arc<AtomicUsize>,
+}
+
+impl CountingQuery {
+    fn new(inner: impl Query) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            term_lookups: Default::default(),
+        }
+    }
+
+    fn term_lookups(&self) -> usize {
+        self.term_lookups.load(Ordering::SeqCst)
+    }
+}
+
+impl Query for CountingQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        self.inner.weight(enable_scoring)
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.term_lookups.fetch_add(1, Ordering::SeqCst);
+        self.inner.query_terms(visitor)
+    }
+}
+
+#[tokio::test]
+async fn results_highlight_the_words_of_the_query() {
+    let index = index().await;
+    let query = Doc::title_field().phrase("report");
+
+    let results = index
+        .query(&query, 10)
+        .highlight(Doc::title_field(), 200)
+        .execute_results()
+        .unwrap();
+
+    let hit = results
+        .hits
+        .iter()
+        .find(|hit| hit.model.id() == "a")
+        .unwrap();
+    assert_eq!(hit.highlights.len(), 1);
+    let highlight = hit.highlight("title").unwrap();
+    assert_eq!(highlight.fragment, "The annual report of <the> company");
+    assert_eq!(highlight.ranges, vec![Range { start: 11, end: 17 }]);
+    assert_eq!(
+        highlight.html,
+        "The annual <b>report</b> of &lt;the&gt; company"
+    );
+    assert!(hit.highlight("tag").is_none());
+}
+
+#[tokio::test]
+async fn only_results_build_snippet_generators() {
+    let index = index().await;
+    let query = CountingQuery::new(Doc::title_field().phrase("report"));
+
+    let models = index
+        .query(&query, 10)
+        .highlight(Doc::title_field(), 200)
+        .execute()
+        .unwrap();
+    assert_eq!(models.len(), 2);
+    let page = index
+        .query(&query, 1)
+        .highlight(Doc::title_field(), 200)
+        .execute_page()
+        .unwrap();
+    assert_eq!(page.models.len(), 1);
+    let async_models = index
+        .query(&query, 10)
+        .highlight(Doc::title_field(), 200)
+        .execute_async()
+        .await
+        .unwrap();
+    assert_eq!(ids(&async_models), ids(&models));
+    assert_eq!(query.term_lookups(), 0);
+
+    let results = index
+        .query(&query, 10)
+        .highlight(Doc::title_field(), 200)
+        .execute_results()
+        .unwrap();
+    assert_eq!(query.term_lookups(), 1);
+    assert!(results.hits.iter().all(|hit| hit.highlights.len() == 1));
+}
+
+#[tokio::test]
+async fn fields_without_the_words_get_an_empty_highlight() {
+    let index = index().await;
+
+    let results = index
+        .query(&Doc::title_field().phrase("nothing"), 10)
+        .highlight(Doc::title_field(), 10)
+        .execute_results()
+        .unwrap();
+    let highlight = results.hits[0].highlight("title").unwrap();
+    assert_eq!(highlight.fragment, "Nothing to");
+    assert_eq!(highlight.ranges, vec![Range { start: 0, end: 7 }]);
+
+    let results = index
+        .query(&Doc::id_field().eq("a"), 10)
+        .highlight(Doc::title_field(), 10)
+        .execute_results()
+        .unwrap();
+    let highlight = results.hits[0].highlight("title").unwrap();
+    assert!(highlight.fragment.is_empty());
+    assert!(highlight.ranges.is_empty());
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{ram_builder, Doc};
+use tantivy::{query::AllQuery, store::Compressor, Order};
+
+#[tokio::test]
+async fn new_indexes_get_created_with_the_doc_store_settings() {
+    let index = ram_builder()
+        .with_docstore_compression(Compressor::None)
+        .with_docstore_blocksize(4096)
+        .build();
+
+    let settings = index.get_tantivy_backend().index.settings().clone();
+    assert_eq!(settings.docstore_compression, Compressor::None);
+    assert_eq!(settings.docstore_blocksize, 4096);
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn index_sort_orders_the_documents_of_each_segment() {
+    let index = ram_builder()
+        .with_index_sort(Doc::popularity_field(), Order::Desc)
+        .with_read_your_writes(true)
+        .build();
+    let sort = index
+        .get_tantivy_backend()
+        .index
+        .settings()
+        .sort_by_field
+        .clone()
+        .unwrap();
+    assert_eq!(sort.field, "popularity");
+    assert_eq!(sort.order, Order::Desc);
+
+    index
+        .add(&[
+            Doc::new("low", "title").popularity(1.0),
+            Doc::new("high", "title").popularity(3.0),
+            Doc::new("middle", "title").popularity(2.0),
+        ])
+        .await
+        .unwrap();
+
+    // Every document scores the same, so they come back in the order they are stored in
+    let docs = index.query(&AllQuery, 10).execute().unwrap();
+    assert_eq!(common::ids(&docs), ["high", "middle", "low"]);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{all_ids, builder_in, ram_builder, ram_index, Doc, FailingDirectory};
+use futures::{channel::mpsc, SinkExt};
+use tantivy_ext::IngestPolicy;
+
+#[test]
+fn commits_partial_batches_within_a_second_by_default() {
+    let policy = IngestPolicy::default();
+    assert_eq!(policy.batch_size, 1000);
+    assert_eq!(policy.max_batch_delay, Some(Duration::from_secs(1)));
+}
+
+#[tokio::test]
+async fn counts_added_and_replaced_models() {
+    let index = ram_index();
+    index
+        .add(&[Doc::new("a", "first"), Doc::new("b", "second")])
+        .await
+        .unwrap();
+
+    let summary = index
+        .ingest_iter(vec![
+            Doc::new("a", "replaces a stored model"),
+            Doc::new("c", "new"),
+            Doc::new("d", "new"),
+        ])
+        .await;
+
+    assert_eq!(summary.added, 2);
+    assert_eq!(summary.replaced, 1);
+    assert_eq!(summary.failed, 0);
+    assert!(summary.errors.is_empty());
+    assert_eq!(all_ids(&index), ["a", "b", "c", "d"]);
+}
+
+#[tokio::test]
+async fn counts_duplicates_within_a_batch_as_replaced() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "stored")]).await.unwrap();
+
+    let summary = index
+        .ingest_iter(vec![
+            Doc::new("a", "replaces the stored model"),
+            Doc::new("b", "first"),
+            Doc::new("b", "second"),
+            Doc::new("b", "third"),
+        ])
+        .await;
+
+    assert_eq!(summary.added, 1);
+    assert_eq!(summary.replaced, 3);
+    assert_eq!(all_ids(&index), ["a", "b"]);
+}
+
+#[tokio::test]
+async fn splits_the_stream_into_batches() {
+    let index = ram_builder()
+        .with_read_your_writes(true)
+        .with_ingest_policy(IngestPolicy {
+            batch_size: 2,
+            max_batch_delay: None,
+        })
+        .build();
+
+    let docs: Vec<_> = (0..5).map(|i| Doc::new(&i.to_string(), "model")).collect();
+    let summary = index.ingest_iter(docs).await;
+
+    assert_eq!(summary.added, 5);
+    assert_eq!(all_ids(&index), ["0", "1", "2", "3", "4"]);
+}
+
+#[tokio::test]
+async fn commits_a_partial_batch_once_the_delay_runs_out() {
+    let index = ram_builder()
+        .with_read_your_writes(true)
+        .with_ingest_policy(IngestPolicy {
+            batch_size: 100,
+            max_batch_delay: Some(Duration::from_millis(50)),
+        })
+        .build();
+
+    let (mut sender, receiver) = mpsc::channel(10);
+    let ingesting = index.ingest(receiver);
+    let producing = async {
+        sender.send(Doc::new("a", "early")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        // The first model got committed on its own while the stream was idle
+        let committed = all_ids(&index);
+        sender.send(Doc::new("b", "late")).await.unwrap();
+        drop(sender);
+        committed
+    };
+
+    let (summary, committed) = tokio::join!(ingesting, producing);
+    assert_eq!(committed, ["a"]);
+    assert_eq!(summary.added, 2);
+    assert_eq!(all_ids(&index), ["a", "b"]);
+}
+
+#[tokio::test]
+async fn a_batch_whose_commit_fails_is_rolled_back() {
+    let directory = FailingDirectory::default();
+    let index = builder_in(directory.clone())
+        .with_read_your_writes(true)
+        .build();
+
+    directory.fail_commits(true);
+    let summary = index.ingest_iter(vec![Doc::new("leaked", "first")]).await;
+    directory.fail_commits(false);
+    assert_eq!(summary.failed, 1);
+    assert_eq!(summary.errors.len(), 1);
+
+    let summary = index.ingest_iter(vec![Doc::new("b", "second")]).await;
+    assert_eq!(summary.added, 1);
+    assert_eq!(all_ids(&index), ["b"]);
+}
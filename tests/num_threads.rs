@@ -0,0 +1,34 @@
+mod common;
+
+use common::{all_ids, ram_builder, Doc};
+use tantivy::TantivyError;
+use tantivy_ext::MEMORY_BUDGET_PER_THREAD_MIN;
+
+#[tokio::test]
+async fn a_budget_that_fits_every_thread_builds() {
+    let index = ram_builder()
+        .with_num_threads(2)
+        .with_memory_budget(2 * MEMORY_BUDGET_PER_THREAD_MIN)
+        .with_read_your_writes(true)
+        .build();
+
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+    assert_eq!(all_ids(&index), ["a"]);
+}
+
+#[test]
+fn a_budget_too_small_for_the_threads_is_rejected() {
+    let result = ram_builder()
+        .with_num_threads(4)
+        .with_memory_budget(3 * MEMORY_BUDGET_PER_THREAD_MIN)
+        .try_build();
+
+    assert!(matches!(result, Err(TantivyError::InvalidArgument(_))));
+}
+
+#[test]
+fn zero_threads_are_rejected() {
+    let result = ram_builder().with_num_threads(0).try_build();
+
+    assert!(matches!(result, Err(TantivyError::InvalidArgument(_))));
+}
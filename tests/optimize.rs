@@ -0,0 +1,106 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use common::{all_ids, builder_in, ram_builder, Doc, FailingDirectory};
+use tantivy::{
+    indexer::{MergeCandidate, MergePolicy},
+    SegmentMeta, TantivyError,
+};
+use tantivy_ext::{IndexMergePolicy, SearchIndex};
+
+/// Counts how often the writer asks it for merges, which happens after every commit
+#[derive(Debug, Default)]
+struct CountingMergePolicy(AtomicUsize);
+
+impl CountingMergePolicy {
+    fn calls(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl MergePolicy for CountingMergePolicy {
+    fn compute_merge_candidates(&self, _segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Vec::new()
+    }
+}
+
+fn unmerged_index() -> SearchIndex<Doc> {
+    ram_builder()
+        .with_merge_policy(IndexMergePolicy::NoMerge)
+        .with_read_your_writes(true)
+        .build()
+}
+
+fn segment_count(index: &SearchIndex<Doc>) -> usize {
+    index
+        .get_tantivy_backend()
+        .index
+        .searchable_segment_ids()
+        .unwrap()
+        .len()
+}
+
+#[tokio::test]
+async fn without_merging_every_commit_adds_a_segment() {
+    let index = unmerged_index();
+    for id in ["a", "b", "c", "d"] {
+        index.add(&[Doc::new(id, "title")]).await.unwrap();
+    }
+
+    assert_eq!(segment_count(&index), 4);
+}
+
+#[tokio::test]
+async fn optimize_merges_down_to_the_requested_segment_count() {
+    let index = unmerged_index();
+    for id in ["a", "b", "c", "d", "e"] {
+        index.add(&[Doc::new(id, "title")]).await.unwrap();
+    }
+
+    index.optimize(2).await.unwrap();
+    assert!(segment_count(&index) <= 2);
+    index.optimize(1).await.unwrap();
+    assert_eq!(segment_count(&index), 1);
+
+    assert_eq!(all_ids(&index), ["a", "b", "c", "d", "e"]);
+}
+
+#[tokio::test]
+async fn optimize_to_zero_segments_is_rejected() {
+    let index = unmerged_index();
+
+    let result = index.optimize(0).await;
+
+    assert!(matches!(result, Err(TantivyError::InvalidArgument(_))));
+}
+
+#[tokio::test]
+async fn the_merge_policy_survives_a_rolled_back_transaction() {
+    let directory = FailingDirectory::default();
+    let policy = Arc::new(CountingMergePolicy::default());
+    let index = builder_in(directory.clone())
+        .with_merge_policy(IndexMergePolicy::Custom(policy.clone()))
+        .with_read_your_writes(true)
+        .build();
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+    assert!(policy.calls() > 0);
+
+    directory.fail_commits(true);
+    let failed = index
+        .transaction()
+        .add(&[Doc::new("b", "rolled back")])
+        .commit()
+        .await;
+    directory.fail_commits(false);
+    assert!(failed.is_err());
+
+    let before = policy.calls();
+    index.add(&[Doc::new("c", "third")]).await.unwrap();
+    assert!(policy.calls() > before);
+    assert_eq!(all_ids(&index), ["a", "c"]);
+}
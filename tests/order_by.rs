@@ -0,0 +1,181 @@
+mod common;
+
+use common::{ids, ram_index, Doc};
+use tantivy::{query::AllQuery, DateTime, Order};
+use tantivy_ext::{ext_field::ExtField, index::query::sort::ExtSortType, SearchIndex, SortValue};
+
+/// Every commit makes a segment of its own, each with its own dictionary of categories
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    let segments = [
+        vec![
+            Doc::new("a", "")
+                .category("banana")
+                .views(3)
+                .rank(-2)
+                .date_secs(30),
+            Doc::new("b", "")
+                .category("date")
+                .views(1)
+                .rank(5)
+                .date_secs(10),
+        ],
+        vec![
+            Doc::new("c", "")
+                .category("apple")
+                .views(3)
+                .rank(0)
+                .date_secs(50),
+            Doc::new("d", "")
+                .category("cherry")
+                .views(2)
+                .rank(-7)
+                .date_secs(20),
+            Doc::new("e", "")
+                .category("banana")
+                .views(5)
+                .rank(1)
+                .date_secs(40),
+        ],
+        vec![Doc::new("f", "")
+            .category("elder")
+            .views(4)
+            .rank(3)
+            .date_secs(60)],
+    ];
+    for docs in segments {
+        index.add(&docs).await.unwrap();
+    }
+    index
+}
+
+/// The ids of every model, ordered by the field and then by id
+fn order<T>(index: &SearchIndex<Doc>, field: ExtField<T>, order: Order) -> Vec<String>
+where
+    T: ExtSortType,
+{
+    ids(&index
+        .query(&AllQuery, 10)
+        .order_by(field, order)
+        .order_by(Doc::id_field(), Order::Asc)
+        .execute()
+        .unwrap())
+}
+
+#[tokio::test]
+async fn orders_by_numbers_and_dates_both_ways() {
+    let index = index().await;
+
+    assert_eq!(
+        order(&index, Doc::views_field(), Order::Asc),
+        ["b", "d", "a", "c", "f", "e"]
+    );
+    assert_eq!(
+        order(&index, Doc::views_field(), Order::Desc),
+        ["e", "f", "a", "c", "d", "b"]
+    );
+    assert_eq!(
+        order(&index, Doc::rank_field(), Order::Asc),
+        ["d", "a", "c", "e", "f", "b"]
+    );
+    assert_eq!(
+        order(&index, Doc::popularity_field(), Order::Desc),
+        ["a", "b", "c", "d", "e", "f"]
+    );
+    assert_eq!(
+        order(&index, Doc::date_field(), Order::Desc),
+        ["f", "c", "e", "a", "d", "b"]
+    );
+}
+
+#[tokio::test]
+async fn orders_by_strings_across_segments() {
+    let index = index().await;
+
+    assert_eq!(
+        order(&index, Doc::category_field(), Order::Asc),
+        ["c", "a", "e", "d", "b", "f"]
+    );
+    assert_eq!(
+        order(&index, Doc::category_field(), Order::Desc),
+        ["f", "b", "d", "a", "e", "c"]
+    );
+}
+
+#[tokio::test]
+async fn later_fields_break_ties() {
+    let index = index().await;
+
+    let docs = index
+        .query(&AllQuery, 10)
+        .order_by(Doc::views_field(), Order::Desc)
+        .order_by(Doc::rank_field(), Order::Asc)
+        .execute()
+        .unwrap();
+    // `a` and `c` both have 3 views
+    assert_eq!(ids(&docs), ["e", "f", "a", "c", "d", "b"]);
+
+    let docs = index
+        .query(&AllQuery, 10)
+        .order_by(Doc::views_field(), Order::Desc)
+        .order_by(Doc::rank_field(), Order::Desc)
+        .execute()
+        .unwrap();
+    assert_eq!(ids(&docs), ["e", "f", "c", "a", "d", "b"]);
+}
+
+#[tokio::test]
+async fn models_and_hits_carry_the_values_they_were_sorted_by() {
+    let index = index().await;
+
+    let results = index
+        .query(&AllQuery, 2)
+        .order_by(Doc::category_field(), Order::Asc)
+        .order_by(Doc::date_field(), Order::Desc)
+        .execute_results()
+        .unwrap();
+
+    let expected = [
+        vec![
+            SortValue::Str("apple".to_string()),
+            SortValue::Date(DateTime::from_timestamp_secs(50)),
+        ],
+        vec![
+            SortValue::Str("banana".to_string()),
+            SortValue::Date(DateTime::from_timestamp_secs(40)),
+        ],
+    ];
+    for (hit, expected) in results.hits.iter().zip(&expected) {
+        assert_eq!(&hit.sort_values, expected);
+        assert_eq!(hit.model.sort_values.values(), expected.as_slice());
+    }
+    assert_eq!(ids(&results.into_models()), ["c", "e"]);
+}
+
+#[tokio::test]
+async fn pages_through_strings_that_are_missing_from_some_segments() {
+    let index = index().await;
+
+    for page_size in 1..=4 {
+        for direction in [Order::Asc, Order::Desc] {
+            let query = || {
+                index
+                    .query(&AllQuery, page_size)
+                    .order_by(Doc::category_field(), direction.clone())
+            };
+            let mut page = query().execute_page().unwrap();
+            let mut seen = ids(&page.models);
+            while let Some(cursor) = page.next {
+                page = query().after(cursor).execute_page().unwrap();
+                seen.extend(ids(&page.models));
+            }
+
+            let all = index
+                .query(&AllQuery, 10)
+                .order_by(Doc::category_field(), direction.clone())
+                .execute()
+                .unwrap();
+            assert_eq!(seen, ids(&all), "page size {}", page_size);
+        }
+    }
+}
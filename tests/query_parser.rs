@@ -0,0 +1,135 @@
+mod common;
+
+use common::{ids, matching, ram_index, Doc};
+use tantivy::TantivyError;
+use tantivy_ext::{Fuzziness, QueryParserConfig, SearchIndex};
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    index
+        .add(&[
+            Doc::new("a", "annual report").category("finance"),
+            Doc::new("b", "report").category("annual"),
+            Doc::new("c", "quarterly numbers").category("finance"),
+        ])
+        .await
+        .unwrap();
+    index
+}
+
+#[tokio::test]
+async fn words_all_have_to_match_by_default() {
+    let index = index().await;
+    let config = QueryParserConfig::new().field(Doc::title_field());
+    let parser = index.typed_query_parser(&config).unwrap();
+
+    assert_eq!(
+        matching(&index, &parser.parse("annual report").unwrap()),
+        ["a"]
+    );
+
+    let parser = index
+        .typed_query_parser(&config.conjunction_by_default(false))
+        .unwrap();
+    assert_eq!(
+        matching(&index, &parser.parse("annual numbers").unwrap()),
+        ["a", "c"]
+    );
+}
+
+#[tokio::test]
+async fn boosted_fields_rank_first() {
+    let index = index().await;
+    let config = |boost| {
+        QueryParserConfig::new()
+            .field(Doc::title_field())
+            .boosted_field(Doc::category_field(), boost)
+            .conjunction_by_default(false)
+    };
+
+    let query = index
+        .typed_query_parser(&config(0.01))
+        .unwrap()
+        .parse("annual")
+        .unwrap();
+    assert_eq!(ids(&index.query(&query, 10).execute().unwrap()), ["a", "b"]);
+
+    let query = index
+        .typed_query_parser(&config(100.0))
+        .unwrap()
+        .parse("annual")
+        .unwrap();
+    assert_eq!(ids(&index.query(&query, 10).execute().unwrap()), ["b", "a"]);
+}
+
+#[tokio::test]
+async fn fuzzy_fields_match_typos() {
+    let index = index().await;
+    let strict = index
+        .typed_query_parser(&QueryParserConfig::new().field(Doc::title_field()))
+        .unwrap();
+    assert!(matching(&index, &strict.parse("raport").unwrap()).is_empty());
+
+    let fuzzy = index
+        .typed_query_parser(
+            &QueryParserConfig::new()
+                .field(Doc::title_field())
+                .fuzzy(Doc::title_field(), 1),
+        )
+        .unwrap();
+    assert_eq!(
+        matching(&index, &fuzzy.parse("raport").unwrap()),
+        ["a", "b"]
+    );
+
+    let prefix = index
+        .typed_query_parser(
+            &QueryParserConfig::new()
+                .field(Doc::title_field())
+                .fuzziness(
+                    Doc::title_field(),
+                    Fuzziness {
+                        prefix: true,
+                        ..Fuzziness::new(0)
+                    },
+                ),
+        )
+        .unwrap();
+    assert_eq!(matching(&index, &prefix.parse("quart").unwrap()), ["c"]);
+}
+
+#[tokio::test]
+async fn fuzzy_distances_above_two_are_rejected() {
+    let index = index().await;
+
+    let two = QueryParserConfig::new()
+        .field(Doc::title_field())
+        .fuzzy(Doc::title_field(), 2);
+    assert!(index.typed_query_parser(&two).is_ok());
+
+    let three = QueryParserConfig::new()
+        .field(Doc::title_field())
+        .fuzzy(Doc::title_field(), 3);
+    match index.typed_query_parser(&three) {
+        Err(TantivyError::InvalidArgument(message)) => assert!(message.contains("title")),
+        other => panic!("expected an invalid argument, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[tokio::test]
+async fn lenient_parsing_returns_the_errors_it_ignored() {
+    let index = index().await;
+    let parser = index
+        .typed_query_parser(&QueryParserConfig::new().field(Doc::title_field()))
+        .unwrap();
+
+    assert!(parser.parse("nonexistent:report").is_err());
+
+    let (query, ignored) = parser.parse_lenient("nonexistent:report annual");
+    assert_eq!(ignored.len(), 1);
+    assert_eq!(matching(&index, &query), ["a"]);
+
+    let (query, ignored) = parser.parse_lenient("annual report");
+    assert!(ignored.is_empty());
+    assert_eq!(matching(&index, &query), ["a"]);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::{builder_in, ids, ram_index_in, Doc};
+use tantivy::{directory::RamDirectory, query::AllQuery, ReloadPolicy};
+use tantivy_ext::{AggregationRequest, QueryParserConfig, Searchable};
+
+#[tokio::test]
+async fn reads_what_the_writer_committed_without_taking_the_lock() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    let read_only = builder_in(directory)
+        .read_only()
+        .with_reload_policy(ReloadPolicy::Manual)
+        .build();
+    assert_eq!(
+        ids(&read_only.query(&AllQuery, 10).execute().unwrap()),
+        ["a"]
+    );
+
+    // The writer keeps working, and its commits show up once the read-only handle reloads
+    index.add(&[Doc::new("b", "second")]).await.unwrap();
+    assert_eq!(read_only.query(&AllQuery, 10).execute().unwrap().len(), 1);
+    read_only.reload().unwrap();
+    assert_eq!(read_only.query(&AllQuery, 10).execute().unwrap().len(), 2);
+}
+
+#[test]
+fn opening_a_directory_without_an_index_fails() {
+    let result = builder_in(RamDirectory::create()).read_only().try_build();
+
+    assert!(result.is_err());
+}
+
+/// Both index types hand out the same reading API
+fn count_first(searchable: &Searchable<Doc>) -> usize {
+    let parser = searchable
+        .typed_query_parser(&QueryParserConfig::new().field(Doc::title_field()))
+        .unwrap();
+    let query = parser.parse("first").unwrap();
+    let mut request = AggregationRequest::new();
+    let stats = request.stats(Doc::views_field());
+    let response = searchable.aggregate(&query, &request).unwrap();
+    response.get(&stats).unwrap().count as usize
+}
+
+#[tokio::test]
+async fn search_index_and_read_only_share_the_reading_api() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index
+        .add(&[Doc::new("a", "first"), Doc::new("b", "second")])
+        .await
+        .unwrap();
+    let read_only = builder_in(directory).read_only().build();
+
+    assert_eq!(count_first(&index), 1);
+    assert_eq!(count_first(&read_only), 1);
+}
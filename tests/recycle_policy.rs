@@ -0,0 +1,96 @@
+mod common;
+
+use common::{all_ids, ram_builder, Doc};
+use tantivy_ext::{IndexMergePolicy, RecycleEvent, RecyclePolicy, RecycleReason, SearchIndex};
+use tokio::sync::broadcast;
+
+fn index_with(policy: RecyclePolicy) -> (SearchIndex<Doc>, broadcast::Receiver<RecycleEvent>) {
+    let index = ram_builder()
+        .with_recycle_policy(policy)
+        .with_merge_policy(IndexMergePolicy::NoMerge)
+        .with_read_your_writes(true)
+        .build();
+    let events = index.subscribe_recycle_events();
+    (index, events)
+}
+
+fn docs(ids: &[&str]) -> Vec<Doc> {
+    ids.iter().map(|id| Doc::new(id, "title")).collect()
+}
+
+#[tokio::test]
+async fn recycles_once_more_entries_than_the_limit_were_processed() {
+    let (index, mut events) = index_with(RecyclePolicy::EntriesProcessed(2));
+
+    index.add(&docs(&["a", "b"])).await.unwrap();
+    assert!(events.try_recv().is_err());
+
+    index.add(&docs(&["c"])).await.unwrap();
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.reason, RecycleReason::EntriesProcessed);
+    assert_eq!(event.entries_processed, 3);
+
+    // The count starts over with the new writer
+    index.add(&docs(&["d", "e"])).await.unwrap();
+    assert!(events.try_recv().is_err());
+    assert_eq!(all_ids(&index), ["a", "b", "c", "d", "e"]);
+}
+
+#[tokio::test]
+async fn recycles_once_more_bytes_than_the_limit_were_indexed() {
+    let (index, mut events) = index_with(RecyclePolicy::BytesIndexed(100));
+
+    index.add(&[Doc::new("a", "short")]).await.unwrap();
+    assert!(events.try_recv().is_err());
+
+    index
+        .add(&[Doc::new("b", &"long title ".repeat(20))])
+        .await
+        .unwrap();
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.reason, RecycleReason::BytesIndexed);
+    assert!(event.bytes_indexed > 100);
+}
+
+#[tokio::test]
+async fn recycles_once_there_are_more_segments_than_the_limit() {
+    let (index, mut events) = index_with(RecyclePolicy::SegmentCount(2));
+
+    index.add(&docs(&["a"])).await.unwrap();
+    index.add(&docs(&["b"])).await.unwrap();
+    assert!(events.try_recv().is_err());
+
+    index.add(&docs(&["c"])).await.unwrap();
+    assert_eq!(
+        events.try_recv().unwrap().reason,
+        RecycleReason::SegmentCount
+    );
+}
+
+#[tokio::test]
+async fn any_recycles_for_the_first_policy_that_says_so() {
+    let (index, mut events) = index_with(RecyclePolicy::Any(vec![
+        RecyclePolicy::Never,
+        RecyclePolicy::EntriesProcessed(1),
+    ]));
+
+    index.add(&docs(&["a", "b"])).await.unwrap();
+
+    assert_eq!(
+        events.try_recv().unwrap().reason,
+        RecycleReason::EntriesProcessed
+    );
+}
+
+#[tokio::test]
+async fn never_only_recycles_when_asked_to() {
+    let (index, mut events) = index_with(RecyclePolicy::Never);
+
+    index.add(&docs(&["a", "b", "c"])).await.unwrap();
+    assert!(events.try_recv().is_err());
+
+    index.recycle_writer().await.unwrap();
+    assert_eq!(events.try_recv().unwrap().reason, RecycleReason::Manual);
+    index.add(&docs(&["d"])).await.unwrap();
+    assert_eq!(all_ids(&index), ["a", "b", "c", "d"]);
+}
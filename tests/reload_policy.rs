@@ -0,0 +1,55 @@
+mod common;
+
+use common::{ids, ram_builder, Doc};
+use tantivy::{query::AllQuery, ReloadPolicy};
+
+#[tokio::test]
+async fn manual_reloads_hide_commits_from_queries() {
+    let index = ram_builder()
+        .with_reload_policy(ReloadPolicy::Manual)
+        .build();
+
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    assert!(index.query(&AllQuery, 10).execute().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn read_your_writes_makes_commits_visible_before_returning() {
+    let index = ram_builder()
+        .with_reload_policy(ReloadPolicy::Manual)
+        .with_read_your_writes(true)
+        .build();
+
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    assert_eq!(ids(&index.query(&AllQuery, 10).execute().unwrap()), ["a"]);
+}
+
+#[tokio::test]
+async fn writes_that_look_models_up_leave_manual_reloads_alone() {
+    let index = ram_builder()
+        .with_reload_policy(ReloadPolicy::Manual)
+        .build();
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    let nothing = Doc::id_field().eq("missing").boxed();
+    assert_eq!(index.remove_by_query(nothing).await.unwrap(), 0);
+    let key = Doc::id_field().term("a".to_string());
+    let updated = index
+        .update(key.clone(), |doc| doc.title = "updated".into())
+        .await
+        .unwrap();
+    assert_eq!(updated.unwrap().title(), "updated");
+    let patched = index
+        .patch(key)
+        .set(Doc::views_field(), 10)
+        .apply()
+        .await
+        .unwrap();
+    assert!(patched);
+    let summary = index.ingest_iter(vec![Doc::new("a", "ingested")]).await;
+    assert_eq!(summary.replaced, 1);
+
+    assert!(index.query(&AllQuery, 10).execute().unwrap().is_empty());
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{all_ids, ram_index, Doc};
+
+#[tokio::test]
+async fn removes_the_matching_models_and_counts_them() {
+    let index = ram_index();
+    index
+        .add(&[
+            Doc::new("a", "first").views(1),
+            Doc::new("b", "second").views(5),
+            Doc::new("c", "third").views(10),
+        ])
+        .await
+        .unwrap();
+
+    let query = Doc::views_field().range(5..);
+    let removed = index.remove_by_query(Box::new(query)).await.unwrap();
+
+    assert_eq!(removed, 2);
+    assert_eq!(all_ids(&index), ["a"]);
+}
+
+#[tokio::test]
+async fn removing_nothing_returns_zero() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    let query = Doc::category_field().eq("missing");
+    let removed = index.remove_by_query(Box::new(query)).await.unwrap();
+
+    assert_eq!(removed, 0);
+    assert_eq!(all_ids(&index), ["a"]);
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{ram_index, Doc};
+use tantivy::{collector::TopDocs, query::AllQuery, Order};
+use tantivy_ext::{Field as _, SearchIndex, SortValue};
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    // Separate commits, so the models end up in different segments
+    for (i, title) in [
+        "report",
+        "the annual report",
+        "report of the report",
+        "quarterly numbers",
+        "a report on nothing much at all",
+    ]
+    .iter()
+    .enumerate()
+    {
+        index
+            .add(&[Doc::new(&i.to_string(), title).views(i as u64)])
+            .await
+            .unwrap();
+    }
+    index
+}
+
+#[tokio::test]
+async fn counts_every_match_not_just_the_page() {
+    let index = index().await;
+    let query = Doc::title_field().phrase("report");
+
+    let results = index.query(&query, 2).execute_results().unwrap();
+    assert_eq!(results.total_hits, 4);
+    assert_eq!(results.hits.len(), 2);
+    assert!(results.next.is_some());
+
+    let rest = index
+        .query(&query, 2)
+        .after(results.next.unwrap())
+        .execute_results()
+        .unwrap();
+    assert_eq!(rest.total_hits, 4);
+    assert_eq!(rest.hits.len(), 2);
+
+    let ordered = index
+        .query(&AllQuery, 1)
+        .order_by(Doc::views_field(), Order::Desc)
+        .execute_results()
+        .unwrap();
+    assert_eq!(ordered.total_hits, 5);
+    assert_eq!(ordered.hits[0].sort_values, [SortValue::U64(4)]);
+
+    let none = index
+        .query(&Doc::title_field().phrase("missing"), 2)
+        .execute_results()
+        .unwrap();
+    assert_eq!(none.total_hits, 0);
+    assert!(none.hits.is_empty());
+    assert!(none.next.is_none());
+}
+
+#[tokio::test]
+async fn hits_carry_the_scores_and_addresses_tantivy_found() {
+    let index = index().await;
+    let query = Doc::title_field().phrase("report");
+
+    let results = index.query(&query, 10).execute_results().unwrap();
+
+    let searcher = index.get_tantivy_backend().reader.searcher();
+    let expected = searcher.search(&query, &TopDocs::with_limit(10)).unwrap();
+    let found: Vec<_> = results
+        .hits
+        .iter()
+        .map(|hit| (hit.score, hit.address))
+        .collect();
+    assert_eq!(found, expected);
+
+    for hit in &results.hits {
+        assert_eq!(hit.model.score.tantivy_val(), hit.score);
+        assert!(hit.sort_values.is_empty());
+        let stored = index
+            .scored_doc_to_model((hit.score as f64, hit.address))
+            .unwrap();
+        assert_eq!(stored.id(), hit.model.id());
+    }
+}
+
+#[tokio::test]
+async fn reports_how_long_the_search_took() {
+    let index = index().await;
+
+    let started = Instant::now();
+    let results = index.query(&AllQuery, 10).execute_results().unwrap();
+    let measured = started.elapsed();
+
+    assert!(results.elapsed > Duration::ZERO);
+    assert!(results.elapsed <= measured);
+}
+
+#[tokio::test]
+async fn async_results_match_the_blocking_ones() {
+    let index = index().await;
+    let query = Doc::title_field().phrase("report");
+
+    let blocking = index.query(&query, 3).execute_results().unwrap();
+    let results = index
+        .query(&query, 3)
+        .execute_results_async()
+        .await
+        .unwrap();
+
+    assert_eq!(results.total_hits, blocking.total_hits);
+    let ids = |hits: &[tantivy_ext::Hit<Doc>]| -> Vec<_> {
+        hits.iter().map(|hit| (hit.model.id(), hit.score)).collect()
+    };
+    assert_eq!(ids(&results.hits), ids(&blocking.hits));
+}
+
+#[tokio::test]
+async fn into_models_keeps_the_order_of_the_hits() {
+    let index = index().await;
+
+    let results = index
+        .query(&AllQuery, 10)
+        .order_by(Doc::views_field(), Order::Asc)
+        .execute_results()
+        .unwrap();
+    let models = results.into_models();
+    let ids: Vec<_> = models.iter().map(Doc::id).collect();
+    assert_eq!(ids, ["0", "1", "2", "3", "4"]);
+
+    // `execute` is the plain list of the same models
+    let plain = index
+        .query(&AllQuery, 10)
+        .order_by(Doc::views_field(), Order::Asc)
+        .execute()
+        .unwrap();
+    assert_eq!(plain.iter().map(Doc::id).collect::<Vec<_>>(), ids);
+}
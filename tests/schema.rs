@@ -0,0 +1,67 @@
+mod common;
+
+use common::{all_ids, builder_in, ram_index_in, Doc};
+use tantivy::{
+    directory::RamDirectory,
+    schema::{Schema, INDEXED, STORED},
+    TantivyError,
+};
+use tantivy_ext::{Index as _, SearchIndex};
+
+/// The schema of `Doc` from before `Date` fields became fast
+fn old_schema() -> Schema {
+    let mut builder = Schema::builder();
+    for (_, entry) in Doc::schema().fields() {
+        if entry.name() == "date" {
+            builder.add_date_field("date", INDEXED | STORED);
+        } else {
+            builder.add_field(entry.clone());
+        }
+    }
+    builder.build()
+}
+
+fn assert_rebuild_error<T>(result: tantivy::Result<T>) {
+    match result {
+        Err(TantivyError::SchemaError(message)) => {
+            assert!(message.contains("rebuilt"), "{}", message);
+            assert!(message.contains("changed fields: date"), "{}", message);
+        }
+        Err(err) => panic!("expected a schema error, got {:?}", err),
+        Ok(_) => panic!("expected a schema error"),
+    }
+}
+
+#[test]
+fn an_index_with_an_older_schema_has_to_be_rebuilt() {
+    let directory = RamDirectory::create();
+    tantivy::Index::create(directory.clone(), old_schema(), Default::default()).unwrap();
+
+    assert_rebuild_error(builder_in(directory.clone()).try_build());
+    assert_rebuild_error(builder_in(directory).read_only().try_build());
+}
+
+#[test]
+fn an_index_directory_with_an_older_schema_has_to_be_rebuilt() {
+    let path = std::env::temp_dir().join(format!("tantivy_ext_old_schema_{}", std::process::id()));
+    std::fs::create_dir_all(&path).unwrap();
+    tantivy::Index::create_in_dir(&path, old_schema()).unwrap();
+
+    let opened: tantivy::Result<SearchIndex<Doc>> = Doc::index_builder(path.clone()).try_build();
+    let read_only = Doc::index_builder(path.clone()).read_only().try_build();
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert_rebuild_error(opened);
+    assert_rebuild_error(read_only);
+}
+
+#[tokio::test]
+async fn an_index_with_the_same_schema_opens_again() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+    index.close().await.unwrap();
+
+    let reopened = ram_index_in(directory);
+    assert_eq!(all_ids(&reopened), ["a"]);
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{ids, ram_index, Doc};
+use tantivy::{query::AllQuery, DateTime, Order, Score, TantivyError};
+use tantivy_ext::{ext_field::ExtField, ext_type::ExtF64, Decay, FieldValueFactor, SearchIndex};
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    // Separate commits, so the models end up in different segments
+    index
+        .add(&[Doc::new("a", "first")
+            .popularity(1.0)
+            .views(100)
+            .date_secs(0)])
+        .await
+        .unwrap();
+    index
+        .add(&[Doc::new("b", "second")
+            .popularity(3.0)
+            .views(10)
+            .date_secs(900)])
+        .await
+        .unwrap();
+    index
+        .add(&[Doc::new("c", "third")
+            .popularity(2.0)
+            .views(1)
+            .date_secs(500)])
+        .await
+        .unwrap();
+    index
+}
+
+#[tokio::test]
+async fn tweaks_rank_by_a_fast_field() {
+    let index = index().await;
+
+    let results = index
+        .query(&AllQuery, 10)
+        .tweak_score(|ctx| {
+            let popularity = ctx.fast(Doc::popularity_field())?;
+            Ok(move |doc, score: Score| score * popularity.first(doc).unwrap_or(0.0) as Score)
+        })
+        .execute_results()
+        .unwrap();
+
+    let hits: Vec<_> = results
+        .hits
+        .iter()
+        .map(|hit| (hit.model.id(), hit.score))
+        .collect();
+    assert_eq!(
+        hits,
+        [
+            ("b".to_string(), 3.0),
+            ("c".to_string(), 2.0),
+            ("a".to_string(), 1.0)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn tweaks_run_in_the_order_they_were_added() {
+    let index = index().await;
+
+    let results = index
+        .query(&AllQuery, 1)
+        .tweak_score(|_| Ok(|_, score| score + 1.0))
+        .tweak_score(|_| Ok(|_, score| score * 3.0))
+        .execute_results()
+        .unwrap();
+
+    assert_eq!(results.hits[0].score, 6.0);
+}
+
+#[tokio::test]
+async fn reading_a_field_that_is_not_fast_fails_the_search() {
+    let index = index().await;
+    // Every numeric field of a model is fast, so point a numeric handle at a field that isn't
+    let not_fast = || ExtField::<ExtF64>::new("tag".to_string(), Doc::tag_field().field());
+
+    let tweaked = index
+        .query(&AllQuery, 10)
+        .tweak_score(move |ctx| {
+            let weight = ctx.fast(not_fast())?;
+            Ok(move |doc, score| score * weight.first(doc).unwrap_or(1.0) as Score)
+        })
+        .execute();
+    assert!(matches!(tweaked, Err(TantivyError::SchemaError(_))));
+
+    let boosted = index
+        .query(&AllQuery, 10)
+        .log_boost(not_fast(), 1.0)
+        .execute();
+    assert!(matches!(boosted, Err(TantivyError::SchemaError(_))));
+}
+
+#[tokio::test]
+async fn an_error_while_setting_up_a_tweak_fails_the_search() {
+    let index = index().await;
+
+    let result = index
+        .query(&AllQuery, 10)
+        .tweak_score(|_| Err::<fn(_, _) -> _, _>(TantivyError::InvalidArgument("nope".into())))
+        .execute();
+
+    assert!(matches!(result, Err(TantivyError::InvalidArgument(_))));
+}
+
+#[tokio::test]
+async fn decay_favours_dates_close_to_the_origin() {
+    let index = index().await;
+    let origin = DateTime::from_timestamp_secs(1000);
+
+    let docs = index
+        .query(&AllQuery, 10)
+        .decay(
+            Doc::date_field(),
+            Decay::linear(origin, Duration::from_secs(1000)),
+        )
+        .execute()
+        .unwrap();
+
+    assert_eq!(ids(&docs), ["b", "c", "a"]);
+}
+
+#[tokio::test]
+async fn field_value_factor_multiplies_by_the_value() {
+    let index = index().await;
+
+    let results = index
+        .query(&AllQuery, 10)
+        .field_value_factor(Doc::views_field(), FieldValueFactor::new().factor(2.0))
+        .execute_results()
+        .unwrap();
+
+    let scores: Vec<_> = results.hits.iter().map(|hit| hit.score).collect();
+    assert_eq!(ids(&results.into_models()), ["a", "b", "c"]);
+    assert_eq!(scores, [200.0, 20.0, 2.0]);
+}
+
+#[tokio::test]
+async fn log_boost_ranks_by_the_logarithm_of_the_value() {
+    let index = index().await;
+
+    let results = index
+        .query(&AllQuery, 10)
+        .log_boost(Doc::popularity_field(), 1.0)
+        .execute_results()
+        .unwrap();
+
+    assert_eq!(results.hits[0].model.id(), "b");
+    assert!((results.hits[0].score - 5.0f32.ln()).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn tweaks_apply_to_searches_ordered_by_fields() {
+    let index = index().await;
+
+    let results = index
+        .query(&AllQuery, 10)
+        .order_by(Doc::views_field(), Order::Asc)
+        .tweak_score(|ctx| {
+            let rank = ctx.fast(Doc::popularity_field())?;
+            Ok(move |doc, _| rank.first(doc).unwrap_or(0.0) as Score)
+        })
+        .execute_results()
+        .unwrap();
+
+    let hits: Vec<_> = results
+        .hits
+        .iter()
+        .map(|hit| (hit.model.id(), hit.score))
+        .collect();
+    assert_eq!(
+        hits,
+        [
+            ("c".to_string(), 2.0),
+            ("b".to_string(), 3.0),
+            ("a".to_string(), 1.0)
+        ]
+    );
+}
@@ -3,6 +3,7 @@ use std::{path::PathBuf, time::Duration};
 use tantivy::time::OffsetDateTime;
 use tantivy_ext::TantivySearchIndex;
 
+#[allow(dead_code)]
 #[derive(TantivySearchIndex, Debug)]
 struct MyModel {
     #[tantivy_ext("primary_key")]
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{all_ids, ram_index, ram_index_in, Doc, FailingDirectory};
+use tantivy::query::TermQuery;
+
+#[tokio::test]
+async fn applies_every_operation_with_one_commit() {
+    let index = ram_index();
+    index
+        .add(&[Doc::new("a", "first"), Doc::new("b", "second")])
+        .await
+        .unwrap();
+
+    index
+        .transaction()
+        .add(&[Doc::new("c", "third")])
+        .remove(&[Doc::new("a", "first")])
+        .commit()
+        .await
+        .unwrap();
+
+    assert_eq!(all_ids(&index), ["b", "c"]);
+}
+
+#[tokio::test]
+async fn adding_an_existing_primary_key_replaces_the_model() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "old")]).await.unwrap();
+    index.add(&[Doc::new("a", "new")]).await.unwrap();
+
+    let docs = index
+        .query(&tantivy::query::AllQuery, 10)
+        .execute()
+        .unwrap();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0].title(), "new");
+}
+
+#[tokio::test]
+async fn a_failing_operation_discards_the_whole_transaction() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    // A term query on a field that isn't indexed fails once the writer applies it
+    let not_indexed = TermQuery::new(
+        Doc::popularity_field().term(1.0),
+        tantivy::schema::IndexRecordOption::Basic,
+    );
+    let result = index
+        .transaction()
+        .add(&[Doc::new("b", "second")])
+        .remove_by_query(Box::new(not_indexed))
+        .commit()
+        .await;
+
+    assert!(result.is_err());
+    assert_eq!(all_ids(&index), ["a"]);
+}
+
+#[tokio::test]
+async fn a_failed_commit_does_not_leak_into_the_next_one() {
+    let directory = FailingDirectory::default();
+    let index = ram_index_in(directory.clone());
+
+    directory.fail_commits(true);
+    let failed = index.add(&[Doc::new("leaked", "should not show up")]).await;
+    directory.fail_commits(false);
+    assert!(failed.is_err());
+
+    index.add(&[Doc::new("b", "second")]).await.unwrap();
+    assert_eq!(all_ids(&index), ["b"]);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::{matching, ram_index, Doc};
+use tantivy::DateTime;
+use tantivy_ext::SearchIndex;
+
+async fn index() -> SearchIndex<Doc> {
+    let index = ram_index();
+    index
+        .add(&[
+            Doc::new("a", "The annual report")
+                .category("reports")
+                .date_secs(100)
+                .popularity(1.5)
+                .views(10)
+                .rank(-5),
+            Doc::new("b", "Report of the year")
+                .category("reviews")
+                .date_secs(200)
+                .popularity(2.5)
+                .views(20)
+                .rank(0),
+            Doc::new("c", "Quarterly numbers")
+                .category("drafts")
+                .date_secs(300)
+                .popularity(3.5)
+                .views(30)
+                .rank(5),
+        ])
+        .await
+        .unwrap();
+    index
+}
+
+#[tokio::test]
+async fn text_fields_match_whole_values() {
+    let index = index().await;
+    let category = Doc::category_field();
+
+    assert_eq!(matching(&index, &category.eq("reports")), ["a"]);
+    assert!(matching(&index, &category.eq("report")).is_empty());
+    assert_eq!(
+        matching(&index, &category.one_of(["reports", "drafts"])),
+        ["a", "c"]
+    );
+    assert_eq!(matching(&index, &category.prefix("re")), ["a", "b"]);
+    assert_eq!(matching(&index, &category.fuzzy("raports", 1)), ["a"]);
+    assert_eq!(matching(&index, &category.regex("d.*s").unwrap()), ["c"]);
+}
+
+#[tokio::test]
+async fn tokenized_fields_match_words() {
+    let index = index().await;
+    let title = Doc::title_field();
+
+    assert_eq!(matching(&index, &title.prefix("Rep")), ["a", "b"]);
+    assert_eq!(matching(&index, &title.fuzzy("quartely", 1)), ["c"]);
+    assert_eq!(matching(&index, &title.phrase("annual report")), ["a"]);
+    assert!(matching(&index, &title.phrase("report annual")).is_empty());
+    assert_eq!(matching(&index, &title.phrase("Numbers")), ["c"]);
+    assert!(matching(&index, &title.phrase("")).is_empty());
+    assert_eq!(matching(&index, &title.regex("rep.*").unwrap()), ["a", "b"]);
+}
+
+#[test]
+fn an_invalid_regex_is_an_error() {
+    assert!(Doc::title_field().regex("(unclosed").is_err());
+    assert!(Doc::category_field().regex("[").is_err());
+}
+
+#[tokio::test]
+async fn numeric_fields_match_values_and_ranges() {
+    let index = index().await;
+
+    let views = Doc::views_field();
+    assert_eq!(matching(&index, &views.eq(20)), ["b"]);
+    assert_eq!(matching(&index, &views.one_of([10, 30])), ["a", "c"]);
+    assert_eq!(matching(&index, &views.range(10..30)), ["a", "b"]);
+    assert_eq!(matching(&index, &views.range(20..)), ["b", "c"]);
+
+    let popularity = Doc::popularity_field();
+    assert_eq!(matching(&index, &popularity.eq(2.5)), ["b"]);
+    assert_eq!(matching(&index, &popularity.one_of([1.5, 3.5])), ["a", "c"]);
+    assert_eq!(matching(&index, &popularity.range(..=2.5)), ["a", "b"]);
+
+    let rank = Doc::rank_field();
+    assert_eq!(matching(&index, &rank.eq(-5)), ["a"]);
+    assert_eq!(matching(&index, &rank.one_of([-5, 5])), ["a", "c"]);
+    assert_eq!(matching(&index, &rank.range(..0)), ["a"]);
+    assert_eq!(matching(&index, &rank.range(-5..=5)), ["a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn date_fields_match_values_and_ranges() {
+    let index = index().await;
+    let date = Doc::date_field();
+    let secs = DateTime::from_timestamp_secs;
+
+    assert_eq!(matching(&index, &date.eq(secs(200))), ["b"]);
+    assert_eq!(
+        matching(&index, &date.one_of([secs(100), secs(300)])),
+        ["a", "c"]
+    );
+    assert_eq!(matching(&index, &date.range(secs(150)..)), ["b", "c"]);
+    assert_eq!(
+        matching(&index, &date.range(secs(100)..secs(300))),
+        ["a", "b"]
+    );
+}
+
+#[tokio::test]
+async fn boosting_a_query_keeps_its_matches() {
+    let index = index().await;
+    let query = Doc::category_field().eq("reviews").boost(3.0);
+
+    assert_eq!(matching(&index, &query), ["b"]);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::{all_ids, ram_index, Doc};
+use tantivy::query::AllQuery;
+
+#[tokio::test]
+async fn update_rewrites_the_stored_model() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "first").views(1)]).await.unwrap();
+
+    let key = Doc::id_field().term("a".to_string());
+    let updated = index
+        .update(key, |doc| doc.views = 2.into())
+        .await
+        .unwrap()
+        .expect("the model exists");
+
+    assert_eq!(updated.id(), "a");
+    let docs = index.query(&AllQuery, 10).execute().unwrap();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(tantivy_ext::Field::tantivy_val(&docs[0].views), 2);
+}
+
+#[tokio::test]
+async fn update_of_a_missing_key_returns_none() {
+    let index = ram_index();
+    let key = Doc::id_field().term("missing".to_string());
+
+    let updated = index.update(key, |doc| doc.views = 2.into()).await.unwrap();
+
+    assert!(updated.is_none());
+    assert!(all_ids(&index).is_empty());
+}
+
+#[tokio::test]
+async fn update_that_changes_the_primary_key_removes_the_old_one() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    let key = Doc::id_field().term("a".to_string());
+    index.update(key, |doc| doc.id = "b".into()).await.unwrap();
+
+    assert_eq!(all_ids(&index), ["b"]);
+}
+
+#[tokio::test]
+async fn patch_only_touches_the_fields_that_are_set() {
+    let index = ram_index();
+    index
+        .add(&[Doc::new("a", "first").views(1).category("news")])
+        .await
+        .unwrap();
+
+    let applied = index
+        .patch(Doc::id_field().term("a".to_string()))
+        .set(Doc::views_field(), 7)
+        .apply()
+        .await
+        .unwrap();
+
+    assert!(applied);
+    let docs = index.query(&AllQuery, 10).execute().unwrap();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(tantivy_ext::Field::tantivy_val(&docs[0].views), 7);
+    assert_eq!(tantivy_ext::Field::tantivy_val(&docs[0].category), "news");
+    assert_eq!(docs[0].title(), "first");
+}
+
+#[tokio::test]
+async fn patch_of_a_missing_key_is_not_applied() {
+    let index = ram_index();
+
+    let applied = index
+        .patch(Doc::id_field().term("missing".to_string()))
+        .set(Doc::views_field(), 7)
+        .apply()
+        .await
+        .unwrap();
+
+    assert!(!applied);
+    assert!(all_ids(&index).is_empty());
+}
+
+#[tokio::test]
+async fn concurrent_updates_are_not_lost() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "first").views(0)]).await.unwrap();
+
+    let updates = (0..20).map(|_| {
+        let index = index.clone();
+        tokio::spawn(async move {
+            let key = Doc::id_field().term("a".to_string());
+            index
+                .update(key, |doc| {
+                    let views = tantivy_ext::Field::tantivy_val(&doc.views);
+                    doc.views = (views + 1).into();
+                })
+                .await
+                .unwrap();
+        })
+    });
+    for update in updates.collect::<Vec<_>>() {
+        update.await.unwrap();
+    }
+
+    let docs = index.query(&AllQuery, 10).execute().unwrap();
+    assert_eq!(tantivy_ext::Field::tantivy_val(&docs[0].views), 20);
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::{all_ids, ram_index, ram_index_in, Doc, FailingDirectory};
+use futures::future::join_all;
+use tantivy::{
+    query::{AllQuery, TermQuery},
+    schema::IndexRecordOption,
+};
+use tantivy_ext::Field;
+
+#[tokio::test]
+async fn transactions_queued_up_together_all_get_committed() {
+    let index = ram_index();
+
+    let adds = (0..50).map(|id| {
+        let index = index.clone();
+        async move { index.add(&[Doc::new(&id.to_string(), "title")]).await }
+    });
+    let results = join_all(adds).await;
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(all_ids(&index).len(), 50);
+}
+
+#[tokio::test]
+async fn a_failing_transaction_does_not_spoil_the_rest_of_its_batch() {
+    let index = ram_index();
+    // A term query on a field that isn't indexed can't be applied
+    let not_indexed = || {
+        Box::new(TermQuery::new(
+            Doc::popularity_field().term(1.0),
+            IndexRecordOption::Basic,
+        ))
+    };
+
+    let transactions = (0..10).map(|id| {
+        let index = index.clone();
+        async move {
+            let transaction = index
+                .transaction()
+                .add(&[Doc::new(&id.to_string(), "title")]);
+            let transaction = if id % 3 == 0 {
+                transaction.remove_by_query(not_indexed())
+            } else {
+                transaction
+            };
+            transaction.commit().await
+        }
+    });
+    let results = join_all(transactions).await;
+
+    for (id, result) in results.iter().enumerate() {
+        assert_eq!(result.is_err(), id % 3 == 0, "transaction {}", id);
+    }
+    assert_eq!(all_ids(&index), ["1", "2", "4", "5", "7", "8"]);
+}
+
+#[tokio::test]
+async fn a_batch_whose_commit_fails_is_rolled_back_as_a_whole() {
+    let directory = FailingDirectory::default();
+    let index = ram_index_in(directory.clone());
+
+    directory.fail_commits(true);
+    let adds = (0..10).map(|id| {
+        let index = index.clone();
+        async move { index.add(&[Doc::new(&id.to_string(), "title")]).await }
+    });
+    let results = join_all(adds).await;
+    directory.fail_commits(false);
+    assert!(results.iter().all(Result::is_err));
+
+    index.add(&[Doc::new("after", "title")]).await.unwrap();
+    assert_eq!(all_ids(&index), ["after"]);
+}
+
+#[tokio::test]
+async fn update_runs_again_when_another_write_changes_the_model_in_between() {
+    let index = ram_index();
+    index.add(&[Doc::new("a", "first").views(1)]).await.unwrap();
+    let calls = AtomicUsize::new(0);
+
+    let key = Doc::id_field().term("a".to_string());
+    let updated = index
+        .update(key, |doc| {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                // The writer task isn't waiting on this closure, so it's free to take another write in the meantime
+                let concurrent = Doc::new("a", "first").views(100).category("changed");
+                futures::executor::block_on(index.add(&[concurrent])).unwrap();
+            }
+            doc.views = (doc.views.tantivy_val() + 1).into();
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(updated.views.tantivy_val(), 101);
+    let docs = index.query(&AllQuery, 10).execute().unwrap();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0].views.tantivy_val(), 101);
+    assert_eq!(docs[0].category.tantivy_val(), "changed");
+}
+
+#[tokio::test]
+async fn update_does_not_run_again_for_writes_to_other_models() {
+    let index = ram_index();
+    index
+        .add(&[Doc::new("a", "first"), Doc::new("b", "second")])
+        .await
+        .unwrap();
+    let calls = AtomicUsize::new(0);
+
+    let key = Doc::id_field().term("a".to_string());
+    index
+        .update(key, |doc| {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                let other = Doc::new("b", "changed");
+                futures::executor::block_on(index.add(&[other])).unwrap();
+            }
+            doc.views = 1.into();
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
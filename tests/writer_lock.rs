@@ -0,0 +1,145 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{all_ids, builder_in, ram_index_in, Doc};
+use tantivy::{
+    directory::{error::LockError, RamDirectory, INDEX_WRITER_LOCK},
+    Directory, TantivyError,
+};
+
+fn is_lock_busy<T>(result: &tantivy::Result<T>) -> bool {
+    matches!(
+        result,
+        Err(TantivyError::LockFailure(LockError::LockBusy, _))
+    )
+}
+
+#[tokio::test]
+async fn build_async_gives_up_after_the_lock_timeout() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+
+    let started = Instant::now();
+    let second = builder_in(directory)
+        .with_lock_timeout(Duration::from_millis(300))
+        .build_async()
+        .await;
+
+    assert!(is_lock_busy(&second));
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    // The lock was left alone, so the first writer keeps working
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+    assert_eq!(all_ids(&index), ["a"]);
+}
+
+#[test]
+fn try_build_waits_for_the_lock_timeout_too() {
+    let directory = RamDirectory::create();
+    let _index = builder_in(directory.clone()).build();
+
+    let started = Instant::now();
+    let second = builder_in(directory)
+        .with_lock_timeout(Duration::from_millis(300))
+        .try_build();
+
+    assert!(is_lock_busy(&second));
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn build_async_takes_the_lock_once_it_gets_released() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    let closing = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        index.close().await.unwrap();
+    });
+    let second = builder_in(directory)
+        .with_lock_timeout(Duration::from_secs(5))
+        .build_async()
+        .await
+        .unwrap();
+    closing.await.unwrap();
+
+    assert_eq!(all_ids(&second), ["a"]);
+}
+
+/// A directory holding an index, along with a writer lock that nobody holds anymore
+async fn stale_lock() -> RamDirectory {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+    index.close().await.unwrap();
+    directory
+        .atomic_write(&INDEX_WRITER_LOCK.filepath, b"")
+        .unwrap();
+    directory
+}
+
+#[tokio::test]
+async fn a_stale_lock_is_left_alone_unless_recovery_is_enabled() {
+    let directory = stale_lock().await;
+
+    let second = builder_in(directory.clone())
+        .with_lock_timeout(Duration::from_millis(100))
+        .build_async()
+        .await;
+    assert!(is_lock_busy(&second));
+    assert!(directory.exists(&INDEX_WRITER_LOCK.filepath).unwrap());
+}
+
+#[tokio::test]
+async fn stale_lock_recovery_breaks_the_lock_after_the_timeout() {
+    let directory = stale_lock().await;
+
+    let started = Instant::now();
+    let index = builder_in(directory)
+        .with_lock_timeout(Duration::from_millis(200))
+        .with_stale_lock_recovery(true)
+        .with_read_your_writes(true)
+        .build_async()
+        .await
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    index.add(&[Doc::new("b", "second")]).await.unwrap();
+    assert_eq!(all_ids(&index), ["a", "b"]);
+}
+
+#[tokio::test]
+async fn try_build_recovers_a_stale_lock_too() {
+    let directory = stale_lock().await;
+
+    let index = builder_in(directory)
+        .with_lock_timeout(Duration::from_millis(100))
+        .with_stale_lock_recovery(true)
+        .try_build()
+        .unwrap();
+
+    assert_eq!(all_ids(&index), ["a"]);
+}
+
+#[tokio::test]
+async fn stale_lock_recovery_leaves_a_lock_that_gets_released_alone() {
+    let directory = RamDirectory::create();
+    let index = ram_index_in(directory.clone());
+    index.add(&[Doc::new("a", "first")]).await.unwrap();
+
+    let closing = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        index.close().await.unwrap();
+    });
+    let second = builder_in(directory)
+        .with_lock_timeout(Duration::from_secs(5))
+        .with_stale_lock_recovery(true)
+        .build_async()
+        .await
+        .unwrap();
+    closing.await.unwrap();
+
+    assert_eq!(all_ids(&second), ["a"]);
+}